@@ -2,6 +2,7 @@
 
 use crate::operations::SearchBy;
 use clap::{Parser, Subcommand, ValueHint};
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, Parser)]
 #[clap(bin_name = "ame", name = "Amethyst", version = env ! ("CARGO_PKG_VERSION"), about = env ! ("CARGO_PKG_DESCRIPTION"), infer_subcommands = true)]
@@ -17,6 +18,26 @@ pub struct Args {
     #[clap(long = "noconfirm", global = true)]
     pub no_confirm: bool,
 
+    /// Keeps building the remaining AUR packages if a build fails and skips the packages depending on it
+    #[clap(long = "keep-going", global = true)]
+    pub keep_going: bool,
+
+    /// Prints what the operation would do without installing or removing anything
+    #[clap(long = "dry-run", global = true)]
+    pub dry_run: bool,
+
+    /// Writes search and query results and plans as JSON documents to stdout
+    #[clap(long, global = true)]
+    pub json: bool,
+
+    /// Ignores cached AUR package information and fetches it again. Also pulls the PKGBUILD repositories
+    #[clap(long, global = true)]
+    pub refresh: bool,
+
+    /// Builds AUR packages in a clean chroot (requires devtools)
+    #[clap(long, global = true)]
+    pub chroot: bool,
+
     /// Loops sudo in the background to ensure it doesn't time out during long builds
     #[clap(long = "sudoloop", global = true)]
     pub sudoloop: bool,
@@ -44,10 +65,22 @@ pub enum Operation {
     #[clap(bin_name = "ame", name = "query", short_flag = 'Q')]
     Query(QueryArgs),
 
+    /// Shows detailed information about repo and AUR packages
+    #[clap(bin_name = "ame", name = "info")]
+    Info(InfoArgs),
+
     /// Upgrades locally installed packages to their latest versions (Default)
     #[clap(bin_name = "ame", name = "upgrade", visible_aliases = & ["-Syu"])]
     Upgrade(UpgradeArgs),
 
+    /// Reinstalls a previously built version of an AUR package
+    #[clap(bin_name = "ame", name = "rollback")]
+    Rollback(RollbackArgs),
+
+    /// Resumes the last AUR installation that didn't finish
+    #[clap(bin_name = "ame", name = "resume")]
+    Resume,
+
     /// Generates shell completions for supported shells (bash, fish, elvish, pwsh)
     #[clap(bin_name = "ame", name = "gencomp", short_flag = 'G')]
     GenComp(GenCompArgs),
@@ -70,9 +103,17 @@ impl Default for Operation {
 #[derive(Default, Debug, Clone, Parser)]
 pub struct InstallArgs {
     /// The name of the package(s) to install or search for
-    #[clap(required = true)]
+    #[clap(required_unless_present_any = &["pkgbuild", "git"])]
     pub packages: Vec<String>,
 
+    /// Builds and installs the PKGBUILD in the given directory like an AUR package
+    #[clap(long, conflicts_with = "repo", value_hint = ValueHint::DirPath)]
+    pub pkgbuild: Vec<PathBuf>,
+
+    /// Builds and installs the PKGBUILD of the given git repository like an AUR package
+    #[clap(long, conflicts_with = "repo", value_hint = ValueHint::Url)]
+    pub git: Vec<String>,
+
     /// Operate only on AUR packages
     #[clap(long, short)]
     pub aur: bool,
@@ -112,6 +153,21 @@ pub struct QueryArgs {
     pub info: Option<String>,
 }
 
+#[derive(Default, Debug, Clone, Parser)]
+pub struct InfoArgs {
+    /// Only looks up packages in the AUR
+    #[clap(long, short)]
+    pub aur: bool,
+
+    /// Only looks up packages in the repos
+    #[clap(long, short)]
+    pub repo: bool,
+
+    /// The packages to show information about
+    #[clap(required = true)]
+    pub packages: Vec<String>,
+}
+
 #[derive(Default, Debug, Clone, Parser)]
 pub struct UpgradeArgs {
     /// Upgrades only repo/native packages
@@ -121,6 +177,32 @@ pub struct UpgradeArgs {
     /// Upgrades only from the AUR
     #[clap(long, short)]
     pub aur: bool,
+
+    /// Also reinstall AUR packages whose local version is newer than the AUR version
+    #[clap(long)]
+    pub allow_downgrade: bool,
+
+    /// Also checks VCS (-git, -svn, -hg) packages for new upstream commits
+    #[clap(long)]
+    pub devel: bool,
+
+    /// AUR packages to skip during this upgrade (comma separated)
+    #[clap(long, value_delimiter = ',')]
+    pub ignore: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, Parser)]
+pub struct RollbackArgs {
+    /// The package to roll back
+    #[clap(required = true)]
+    pub package: String,
+
+    /// The version to install. Lists all kept versions if omitted
+    pub version: Option<String>,
+
+    /// Adds the package to IgnorePkg in pacman.conf so that it isn't upgraded again
+    #[clap(long)]
+    pub ignore: bool,
 }
 
 #[derive(Default, Debug, Clone, Parser)]
@@ -67,3 +67,158 @@ impl GitPullBuilder {
         }
     }
 }
+
+/// The hash of git's empty tree used to diff against when nothing was reviewed yet
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+#[derive(Debug, Default)]
+pub struct GitDiffBuilder {
+    directory: PathBuf,
+    from: Option<String>,
+    excluded: Vec<String>,
+}
+
+impl GitDiffBuilder {
+    pub fn directory<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.directory = path.as_ref().into();
+
+        self
+    }
+
+    /// The revision to diff against HEAD. Diffs against an empty tree if not set
+    pub fn from<S: ToString>(mut self, from: Option<S>) -> Self {
+        self.from = from.map(|f| f.to_string());
+
+        self
+    }
+
+    /// Excludes a file from the diff
+    pub fn exclude<S: ToString>(mut self, file: S) -> Self {
+        self.excluded.push(file.to_string());
+
+        self
+    }
+
+    pub async fn diff(self) -> AppResult<String> {
+        let mut command = ShellCommand::git()
+            .arg("-C")
+            .arg(self.directory)
+            .arg("diff")
+            .arg(self.from.as_deref().unwrap_or(EMPTY_TREE))
+            .arg("HEAD")
+            .arg("--")
+            .arg(".");
+
+        for file in self.excluded {
+            command = command.arg(format!(":(exclude){file}"));
+        }
+        let result = command.wait_with_output().await?;
+
+        if result.status.success() {
+            Ok(result.stdout)
+        } else {
+            Err(AppError::Other(result.stderr))
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GitRefBuilder {
+    directory: PathBuf,
+    reference: String,
+}
+
+impl GitRefBuilder {
+    pub fn directory<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.directory = path.as_ref().into();
+
+        self
+    }
+
+    pub fn reference<S: ToString>(mut self, reference: S) -> Self {
+        self.reference = reference.to_string();
+
+        self
+    }
+
+    /// Returns the commit the reference points to if it exists
+    pub async fn resolve(self) -> AppResult<Option<String>> {
+        let result = ShellCommand::git()
+            .arg("-C")
+            .arg(self.directory)
+            .arg("rev-parse")
+            .arg("--verify")
+            .arg("--quiet")
+            .arg(self.reference)
+            .wait_with_output()
+            .await?;
+
+        if result.status.success() {
+            Ok(Some(result.stdout.trim().to_string()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Points the reference to the current HEAD
+    pub async fn update_to_head(self) -> AppResult<()> {
+        let result = ShellCommand::git()
+            .arg("-C")
+            .arg(self.directory)
+            .arg("update-ref")
+            .arg(self.reference)
+            .arg("HEAD")
+            .wait_with_output()
+            .await?;
+
+        if result.status.success() {
+            Ok(())
+        } else {
+            Err(AppError::Other(result.stderr))
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GitShowBuilder {
+    directory: PathBuf,
+    revision: String,
+    file: String,
+}
+
+impl GitShowBuilder {
+    pub fn directory<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.directory = path.as_ref().into();
+
+        self
+    }
+
+    pub fn revision<S: ToString>(mut self, revision: S) -> Self {
+        self.revision = revision.to_string();
+
+        self
+    }
+
+    pub fn file<S: ToString>(mut self, file: S) -> Self {
+        self.file = file.to_string();
+
+        self
+    }
+
+    /// Returns the content of the file at the given revision
+    pub async fn show(self) -> AppResult<String> {
+        let result = ShellCommand::git()
+            .arg("-C")
+            .arg(self.directory)
+            .arg("show")
+            .arg(format!("{}:{}", self.revision, self.file))
+            .wait_with_output()
+            .await?;
+
+        if result.status.success() {
+            Ok(result.stdout)
+        } else {
+            Err(AppError::Other(result.stderr))
+        }
+    }
+}
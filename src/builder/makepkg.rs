@@ -21,6 +21,8 @@ pub struct MakePkgBuilder {
     needed: bool,
     no_prepare: bool,
     force: bool,
+    chroot: Option<PathBuf>,
+    chroot_packages: Vec<PathBuf>,
 }
 
 impl MakePkgBuilder {
@@ -83,6 +85,24 @@ impl MakePkgBuilder {
         self
     }
 
+    /// Builds the package in a copy of the given chroot with `makechrootpkg`
+    pub fn chroot<D: AsRef<Path>>(mut self, chroot: Option<D>) -> Self {
+        self.chroot = chroot.map(|c| c.as_ref().into());
+
+        self
+    }
+
+    /// Installs the given package archives into the chroot before building
+    pub fn chroot_packages<I: IntoIterator<Item = P>, P: AsRef<Path>>(
+        mut self,
+        packages: I,
+    ) -> Self {
+        let mut packages = packages.into_iter().map(|p| p.as_ref().into()).collect();
+        self.chroot_packages.append(&mut packages);
+
+        self
+    }
+
     pub async fn run(self) -> AppResult<()> {
         let output = self.build().wait_with_output().await?;
 
@@ -100,12 +120,26 @@ impl MakePkgBuilder {
     /// Executes the makepkg command
     #[tracing::instrument(level = "trace")]
     fn build(self) -> ShellCommand {
-        let mut command = ShellCommand::makepkg().working_dir(self.directory);
+        let mut command = if let Some(chroot) = &self.chroot {
+            let mut command = ShellCommand::makechrootpkg()
+                .working_dir(&self.directory)
+                .arg("-c")
+                .arg("-r")
+                .arg(chroot);
+
+            for package in &self.chroot_packages {
+                command = command.arg("-I").arg(package);
+            }
+            command.arg("--")
+        } else {
+            ShellCommand::makepkg().working_dir(&self.directory)
+        };
 
         if self.clean {
             command = command.arg("-c");
         }
-        if self.no_deps {
+        // dependencies of chroot builds are always installed into the chroot
+        if self.no_deps && self.chroot.is_none() {
             command = command.arg("-d")
         }
         if self.install {
@@ -1,6 +1,6 @@
 use std::path::{Path, PathBuf};
 
-use crate::internal::{commands::ShellCommand, error::AppResult, structs::Options};
+use crate::internal::{alpm::Alpm, commands::ShellCommand, error::AppResult, structs::Options};
 
 #[derive(Debug, Default)]
 pub struct PacmanInstallBuilder {
@@ -72,11 +72,14 @@ impl PacmanInstallBuilder {
             command = command.arg("--needed")
         }
 
-        command
+        let result = command
             .args(self.packages)
             .args(self.files)
             .wait_success()
-            .await
+            .await;
+        Alpm::invalidate_shared();
+
+        result
     }
 }
 
@@ -198,39 +201,12 @@ impl PacmanQueryBuilder {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, serde::Serialize)]
 pub struct BasicPackageInfo {
     pub name: String,
     pub version: String,
 }
 
-#[derive(Default)]
-pub struct PacmanSearchBuilder {
-    query: String,
-}
-
-impl PacmanSearchBuilder {
-    pub fn query<S: AsRef<str>>(mut self, query: S) -> Self {
-        if !self.query.is_empty() {
-            self.query.push(' ');
-        }
-        self.query.push_str(query.as_ref());
-
-        self
-    }
-
-    /// Searches and returns if the execution result was ok
-    pub async fn search(self) -> AppResult<bool> {
-        let result = self.build_command().wait_with_output().await?;
-
-        Ok(result.status.success())
-    }
-
-    fn build_command(self) -> ShellCommand {
-        ShellCommand::pacman().arg("-Ss").arg(self.query)
-    }
-}
-
 #[derive(Default, Debug, Clone)]
 pub struct PacmanUninstallBuilder {
     packages: Vec<String>,
@@ -262,6 +238,9 @@ impl PacmanUninstallBuilder {
             command = command.arg("--noconfirm");
         }
 
-        command.wait_success().await
+        let result = command.wait_success().await;
+        Alpm::invalidate_shared();
+
+        result
     }
 }
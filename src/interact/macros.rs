@@ -15,11 +15,22 @@ macro_rules! prompt {
 #[macro_export]
 /// Macro for prompting the user with a multi select
 macro_rules! multi_select {
+    (default all, $items:expr, $($arg:tt)+) => {
+        $crate::interact::Interact::interact($crate::interact::AmeMultiSelect::new(format!($($arg)+)).items($items).all_selected())
+    };
     ($items:expr, $($arg:tt)+) => {
         $crate::interact::Interact::interact($crate::interact::AmeMultiSelect::new(format!($($arg)+)).items($items))
     }
 }
 
+#[macro_export]
+/// Macro for prompting the user to select one of the given items
+macro_rules! select {
+    ($items:expr, $($arg:tt)+) => {
+        $crate::interact::Interact::interact($crate::interact::AmeFuzzySelect::new(format!($($arg)+)).items($items))
+    };
+}
+
 #[macro_export]
 macro_rules! select_opt {
     ($items:expr, $($arg:tt)+) => {
@@ -7,6 +7,7 @@ use super::{theme::AmeTheme, Interact};
 pub struct AmeMultiSelect {
     prompt: String,
     items: Vec<String>,
+    selected: bool,
 }
 
 impl AmeMultiSelect {
@@ -15,6 +16,7 @@ impl AmeMultiSelect {
         Self {
             prompt: prompt.to_string(),
             items: Vec::new(),
+            selected: false,
         }
     }
 
@@ -24,6 +26,13 @@ impl AmeMultiSelect {
 
         self
     }
+
+    /// Preselects all items so that the user only needs to deselect items
+    pub fn all_selected(&mut self) -> &mut Self {
+        self.selected = true;
+
+        self
+    }
 }
 
 impl Interact for AmeMultiSelect {
@@ -34,6 +43,7 @@ impl Interact for AmeMultiSelect {
             dialoguer::MultiSelect::with_theme(AmeTheme::get())
                 .with_prompt(mem::take(&mut self.prompt))
                 .items(&self.items)
+                .defaults(&vec![self.selected; self.items.len()])
                 .interact()
                 .unwrap()
         })
@@ -6,7 +6,9 @@ use std::{
 
 use alpm::SigLevel;
 use alpm_utils::alpm_with_conf;
+use lazy_static::lazy_static;
 use pacmanconf::Config;
+use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
 
 #[derive(Debug)]
 pub enum Error {
@@ -36,7 +38,6 @@ impl From<pacmanconf::Error> for Error {
 }
 
 pub enum PackageFrom {
-    #[allow(dead_code)]
     LocalDb(String),
 
     #[allow(dead_code)]
@@ -57,10 +58,28 @@ impl AlpmPackage<'_> {
             Self::Loaded(p) => p.name(),
         }
     }
+
+    pub fn version(&self) -> &str {
+        match self {
+            Self::Found(p) => p.version(),
+            Self::Loaded(p) => p.version(),
+        }
+    }
+
+    pub fn build_date(&self) -> i64 {
+        match self {
+            Self::Found(p) => p.build_date(),
+            Self::Loaded(p) => p.build_date(),
+        }
+    }
 }
 
 pub struct Alpm(alpm::Alpm);
 
+lazy_static! {
+    static ref SHARED_ALPM: Mutex<Option<Alpm>> = Mutex::new(None);
+}
+
 impl Alpm {
     #[tracing::instrument(level = "trace")]
     pub fn new() -> Result<Self, Error> {
@@ -70,6 +89,26 @@ impl Alpm {
         Ok(Self(alpm))
     }
 
+    /// Returns the alpm handle shared by all local and sync db lookups.
+    /// The handle is locked until the guard is dropped so it must
+    /// not be held across await points.
+    pub fn shared() -> Result<MappedMutexGuard<'static, Self>, Error> {
+        let mut alpm = SHARED_ALPM.lock();
+
+        if alpm.is_none() {
+            *alpm = Some(Self::new()?);
+        }
+
+        Ok(MutexGuard::map(alpm, |a| a.as_mut().unwrap()))
+    }
+
+    /// Drops the shared handle so that the next lookup reads the
+    /// databases again. Must be called after pacman changed them.
+    pub fn invalidate_shared() {
+        tracing::debug!("Invalidating the shared alpm handler");
+        SHARED_ALPM.lock().take();
+    }
+
     pub fn load(&self, pkg: PackageFrom) -> Result<AlpmPackage, Error> {
         match pkg {
             PackageFrom::LocalDb(name) => {
@@ -0,0 +1,111 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    alpm::{Alpm, PackageFrom},
+    config::Config,
+    error::AppResult,
+    local_repo::LocalRepo,
+    utils::get_cache_dir,
+};
+
+/// A previously built package archive that is kept for rollbacks
+#[derive(Clone, Debug)]
+pub struct CachedArchive {
+    pub path: PathBuf,
+    pub version: String,
+    pub build_date: i64,
+}
+
+fn archive_dir(pkg_name: &str) -> PathBuf {
+    get_cache_dir().join("archives").join(pkg_name)
+}
+
+/// Copies the given archives into the archive cache and removes all archives
+/// exceeding the number of builds to keep per package
+#[tracing::instrument(level = "trace")]
+pub fn store_archives(archives: &[PathBuf]) -> AppResult<()> {
+    let keep = Config::get().build.keep_archives;
+
+    if keep == 0 {
+        return Ok(());
+    }
+    let mut names = Vec::new();
+
+    for archive in archives {
+        let name = read_archive(archive)?.0;
+        let dir = archive_dir(&name);
+        fs::create_dir_all(&dir)?;
+
+        if let Some(file_name) = archive.file_name() {
+            fs::copy(archive, dir.join(file_name))?;
+        }
+        names.push(name);
+    }
+    for name in names {
+        for old in cached_archives(&name)?.into_iter().skip(keep) {
+            tracing::debug!("Removing old archive {}", old.path.display());
+            fs::remove_file(old.path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns all cached archives of a package starting with the most recent build
+#[tracing::instrument(level = "trace")]
+pub fn cached_archives(pkg_name: &str) -> AppResult<Vec<CachedArchive>> {
+    let dir = archive_dir(pkg_name);
+
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut archives = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if !path.is_file() || path.extension().and_then(|e| e.to_str()) == Some("sig") {
+            continue;
+        }
+        let (_, version, build_date) = read_archive(&path)?;
+        archives.push(CachedArchive {
+            path,
+            version,
+            build_date,
+        });
+    }
+    archives.sort_by_key(|a| std::cmp::Reverse(a.build_date));
+
+    Ok(archives)
+}
+
+/// Returns an archive of the given package version from the archive cache
+/// or the local repository
+#[tracing::instrument(level = "trace")]
+pub fn find_archive(pkg_name: &str, version: &str) -> AppResult<Option<PathBuf>> {
+    if let Some(archive) = cached_archives(pkg_name)?
+        .into_iter()
+        .find(|a| a.version == version)
+    {
+        return Ok(Some(archive.path));
+    }
+    match LocalRepo::from_config() {
+        Some(repo) => repo.find_archive(pkg_name, version),
+        None => Ok(None),
+    }
+}
+
+/// Reads the name, version and build date of a package archive
+fn read_archive(path: &Path) -> AppResult<(String, String, i64)> {
+    let alpm = Alpm::shared()?;
+    let pkg = alpm.load(PackageFrom::File(path.to_owned()))?;
+
+    Ok((
+        pkg.name().to_owned(),
+        pkg.version().to_owned(),
+        pkg.build_date(),
+    ))
+}
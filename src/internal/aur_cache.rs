@@ -0,0 +1,276 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use flate2::read::GzDecoder;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::Config,
+    dependencies::Dependency,
+    error::{AppError, AppResult},
+    rpc::{aur_url, PackageInfo, PackageMetadata, SearchField},
+    utils::get_cache_dir,
+};
+
+/// The file name of the AUR dump containing the full information of all packages
+const METADATA_DUMP: &str = "packages-meta-ext-v1.json.gz";
+
+/// Set by `--refresh` to ignore everything cached before this run
+static REFRESH: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref STARTED_AT: u64 = now();
+}
+
+/// Ignores all package information cached by previous runs
+pub fn force_refresh() {
+    lazy_static::initialize(&STARTED_AT);
+    REFRESH.store(true, Ordering::Relaxed);
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Returns whether something fetched at the given time can still be used
+fn is_fresh(fetched: u64) -> bool {
+    if REFRESH.load(Ordering::Relaxed) {
+        fetched >= *STARTED_AT
+    } else {
+        now().saturating_sub(fetched) < Config::get().aur.cache_ttl
+    }
+}
+
+/// The AUR package information fetched by previous info requests
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct InfoCache {
+    #[serde(default)]
+    packages: HashMap<String, CachedInfo>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CachedInfo {
+    fetched: u64,
+    info: PackageInfo,
+}
+
+impl InfoCache {
+    fn path() -> PathBuf {
+        get_cache_dir().join("aur_info.json")
+    }
+
+    /// Loads the cache from the cache directory. An unreadable cache is treated as empty.
+    fn load() -> Self {
+        let path = Self::path();
+
+        if !path.exists() {
+            return Self::default();
+        }
+        let cache = fs::File::open(path)
+            .map_err(AppError::from)
+            .and_then(|file| {
+                serde_json::from_reader(BufReader::new(file))
+                    .map_err(|e| AppError::from(e.to_string()))
+            });
+
+        cache.unwrap_or_else(|e| {
+            tracing::warn!("Failed to read the AUR info cache: {e}");
+            Self::default()
+        })
+    }
+
+    /// Writes all entries that didn't expire yet to the cache directory
+    fn save(&mut self) -> AppResult<()> {
+        let ttl = Config::get().aur.cache_ttl;
+        let now = now();
+        self.packages
+            .retain(|_, cached| now.saturating_sub(cached.fetched) < ttl);
+        let content = serde_json::to_string(self).map_err(|e| AppError::from(e.to_string()))?;
+        fs::write(Self::path(), content)?;
+
+        Ok(())
+    }
+}
+
+/// Returns the info cache shared by all requests of this run
+fn info_cache() -> &'static Mutex<InfoCache> {
+    lazy_static! {
+        static ref CACHE: Mutex<InfoCache> = Mutex::new(InfoCache::load());
+    }
+    &CACHE
+}
+
+/// Returns the cached information of the given packages and the names
+/// of the packages that need to be fetched
+pub fn cached_info(names: Vec<String>) -> (Vec<PackageInfo>, Vec<String>) {
+    let cache = info_cache().lock();
+    let mut packages = Vec::new();
+    let mut missing = Vec::new();
+
+    for name in names {
+        match cache.packages.get(&name) {
+            Some(cached) if is_fresh(cached.fetched) => packages.push(cached.info.clone()),
+            _ => missing.push(name),
+        }
+    }
+    tracing::debug!(
+        "{} packages cached, {} to fetch",
+        packages.len(),
+        missing.len()
+    );
+
+    (packages, missing)
+}
+
+/// Adds freshly fetched packages to the cache and writes it to disk
+pub fn store_info(packages: &[PackageInfo]) {
+    if Config::get().aur.cache_ttl == 0 || packages.is_empty() {
+        return;
+    }
+    let mut cache = info_cache().lock();
+    let fetched = now();
+
+    for package in packages {
+        cache.packages.insert(
+            package.metadata.name.clone(),
+            CachedInfo {
+                fetched,
+                info: package.clone(),
+            },
+        );
+    }
+    if let Err(e) = cache.save() {
+        tracing::warn!("Failed to write the AUR info cache: {e}");
+    }
+}
+
+/// The information of all AUR packages read from the metadata dump
+#[derive(Debug)]
+pub struct MetadataDump {
+    packages: HashMap<String, PackageInfo>,
+}
+
+impl MetadataDump {
+    fn path() -> PathBuf {
+        get_cache_dir().join(METADATA_DUMP)
+    }
+
+    /// Downloads the dump if it's missing or expired and reads it
+    #[tracing::instrument(level = "trace")]
+    async fn load() -> AppResult<Self> {
+        let path = Self::path();
+
+        if !path.exists() || !is_fresh(modified_at(&path)?) {
+            let url = format!("{}/{METADATA_DUMP}", aur_url());
+            tracing::debug!("Downloading {url}");
+            let dump = reqwest::get(url)
+                .await
+                .and_then(reqwest::Response::error_for_status)
+                .map_err(|e| AppError::from(e.to_string()))?
+                .bytes()
+                .await
+                .map_err(|e| AppError::from(e.to_string()))?;
+            fs::write(&path, dump)?;
+        }
+        let decoder = GzDecoder::new(BufReader::new(fs::File::open(&path)?));
+        let packages: Vec<PackageInfo> =
+            serde_json::from_reader(BufReader::new(decoder)).map_err(|e| {
+                // downloaded again on the next run
+                let _ = fs::remove_file(&path);
+                AppError::from(format!("Invalid AUR metadata dump: {e}"))
+            })?;
+        tracing::debug!("Read {} packages from the metadata dump", packages.len());
+
+        Ok(Self {
+            packages: packages
+                .into_iter()
+                .map(|p| (p.metadata.name.clone(), p))
+                .collect(),
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PackageInfo> {
+        self.packages.get(name)
+    }
+
+    /// Searches the packages the same way the RPC does
+    pub fn search(&self, query: &str, field: SearchField) -> Vec<PackageMetadata> {
+        let query_lower = query.to_lowercase();
+        let has_dependency = |deps: &[String]| {
+            deps.iter()
+                .filter_map(|d| Dependency::try_from_str(d))
+                .any(|d| d.name == query)
+        };
+
+        self.packages
+            .values()
+            .filter(|p| match field {
+                SearchField::Name => p.metadata.name.contains(&query_lower),
+                SearchField::NameDesc => {
+                    p.metadata.name.contains(&query_lower)
+                        || p.metadata
+                            .description
+                            .as_ref()
+                            .is_some_and(|d| d.to_lowercase().contains(&query_lower))
+                }
+                SearchField::Maintainer => p.metadata.maintainer.as_deref() == Some(query),
+                SearchField::Depends => has_dependency(&p.depends),
+                SearchField::MakeDepends => has_dependency(&p.make_depends),
+                SearchField::OptDepends => p
+                    .opt_depends
+                    .iter()
+                    .any(|d| d.split(':').next().map(str::trim) == Some(query)),
+                SearchField::CheckDepends => has_dependency(&p.check_depends),
+                SearchField::Provides => p.metadata.name == query || has_dependency(&p.provides),
+            })
+            .map(|p| p.metadata.clone())
+            .collect()
+    }
+}
+
+fn modified_at(path: &Path) -> AppResult<u64> {
+    let modified = fs::metadata(path)?
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    Ok(modified)
+}
+
+/// Returns the metadata dump if it's enabled in the config.
+/// The dump is loaded on first use. A dump that failed to load isn't retried during this run.
+pub async fn metadata_dump() -> AppResult<Option<Arc<MetadataDump>>> {
+    lazy_static! {
+        static ref DUMP: tokio::sync::Mutex<Option<Option<Arc<MetadataDump>>>> =
+            tokio::sync::Mutex::new(None);
+    }
+    if !Config::get().aur.metadata_dump {
+        return Ok(None);
+    }
+    // held while loading so that concurrent callers wait for the first load
+    let mut loaded = DUMP.lock().await;
+
+    if let Some(dump) = &*loaded {
+        return Ok(dump.clone());
+    }
+    *loaded = Some(None);
+    let dump = Arc::new(MetadataDump::load().await?);
+    *loaded = Some(Some(dump.clone()));
+
+    Ok(Some(dump))
+}
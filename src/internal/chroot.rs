@@ -0,0 +1,89 @@
+use std::path::{Path, PathBuf};
+
+use super::{alpm::Alpm, archives, commands::ShellCommand, error::AppResult, utils::get_cache_dir};
+
+/// A devtools chroot that packages are built in. The base chroot is kept
+/// under the cache directory and copied for every build by `makechrootpkg`.
+#[derive(Debug)]
+pub struct Chroot {
+    path: PathBuf,
+}
+
+impl Default for Chroot {
+    fn default() -> Self {
+        Self {
+            path: get_cache_dir().join("chroot"),
+        }
+    }
+}
+
+impl Chroot {
+    /// The directory containing the base chroot and its working copies
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn root(&self) -> PathBuf {
+        self.path.join("root")
+    }
+
+    /// Creates the base chroot or updates it if it already exists
+    #[tracing::instrument(level = "trace")]
+    pub async fn prepare(&self) -> AppResult<()> {
+        if self.root().exists() {
+            tracing::info!("Updating build chroot");
+            ShellCommand::arch_nspawn()
+                .arg(self.root())
+                .arg("pacman")
+                .arg("-Syu")
+                .arg("--noconfirm")
+                .wait_success()
+                .await
+        } else {
+            tracing::info!("Creating build chroot in {}", self.path.display());
+            tokio::fs::create_dir_all(&self.path).await?;
+            ShellCommand::mkarchroot()
+                .arg(self.root())
+                .arg("base-devel")
+                .wait_success()
+                .await
+        }
+    }
+
+    /// Returns the archives of packages installed on the host that satisfy the given
+    /// dependencies but aren't available in the repositories. The chroot only has access
+    /// to the repositories so they have to be installed into it for every build.
+    #[tracing::instrument(level = "trace", skip(dependencies))]
+    pub fn host_archives<'a, I: IntoIterator<Item = &'a str>>(
+        dependencies: I,
+    ) -> AppResult<Vec<PathBuf>> {
+        let mut installed = Vec::new();
+        {
+            let alpm = Alpm::shared()?;
+            let handler = alpm.handler();
+
+            for dependency in dependencies {
+                if handler.syncdbs().find_satisfier(dependency).is_some() {
+                    continue;
+                }
+                if let Some(pkg) = handler.localdb().pkgs().find_satisfier(dependency) {
+                    installed.push((pkg.name().to_owned(), pkg.version().to_string()));
+                }
+            }
+        }
+        installed.sort();
+        installed.dedup();
+        let mut found = Vec::new();
+
+        for (name, version) in installed {
+            match archives::find_archive(&name, &version)? {
+                Some(archive) => found.push(archive),
+                None => tracing::warn!(
+                    "No archive of the installed package {name} {version} was found. Building in the chroot might fail"
+                ),
+            }
+        }
+
+        Ok(found)
+    }
+}
@@ -21,6 +21,7 @@ pub struct ShellCommand {
     args: Vec<OsString>,
     elevated: bool,
     working_dir: Option<PathBuf>,
+    stdout_to_stderr: bool,
 }
 
 impl ShellCommand {
@@ -42,10 +43,34 @@ impl ShellCommand {
         Self::new("makepkg")
     }
 
+    pub fn makechrootpkg() -> Self {
+        Self::new("makechrootpkg")
+    }
+
+    pub fn mkarchroot() -> Self {
+        Self::new("mkarchroot")
+    }
+
+    pub fn arch_nspawn() -> Self {
+        Self::new("arch-nspawn")
+    }
+
+    pub fn repo_add() -> Self {
+        Self::new("repo-add")
+    }
+
     pub fn git() -> Self {
         Self::new("git")
     }
 
+    pub fn hg() -> Self {
+        Self::new("hg")
+    }
+
+    pub fn svn() -> Self {
+        Self::new("svn")
+    }
+
     #[allow(dead_code)]
     pub fn bash() -> Self {
         Self::new("bash")
@@ -55,6 +80,10 @@ impl ShellCommand {
         Self::new(Config::read().bin.sudo)
     }
 
+    pub fn sed() -> Self {
+        Self::new("sed")
+    }
+
     pub fn rm() -> Self {
         Self::new("rm")
     }
@@ -71,6 +100,7 @@ impl ShellCommand {
             args: Vec::new(),
             elevated: false,
             working_dir: None,
+            stdout_to_stderr: false,
         }
     }
 
@@ -106,6 +136,14 @@ impl ShellCommand {
         self
     }
 
+    /// Writes the inherited stdout of the child to stderr instead,
+    /// e.g. to keep the json output on stdout parseable
+    pub fn stdout_to_stderr(mut self, enabled: bool) -> Self {
+        self.stdout_to_stderr = enabled;
+
+        self
+    }
+
     /// Waits for the child to exit but returns an error when it exists with a non-zero status code
     pub async fn wait_success(self) -> AppResult<()> {
         let status = self.wait().await?;
@@ -143,6 +181,8 @@ impl ShellCommand {
 
         let (stdout, stderr) = if piped {
             (Stdio::piped(), Stdio::piped())
+        } else if self.stdout_to_stderr {
+            (Stdio::from(std::io::stderr()), Stdio::inherit())
         } else {
             (Stdio::inherit(), Stdio::inherit())
         };
@@ -3,8 +3,10 @@
 use config::FileFormat;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 
+use super::lint::Severity;
 use super::utils::get_config_dir;
 
 #[derive(Default, Debug, Deserialize, Serialize)]
@@ -12,6 +14,16 @@ pub struct Config {
     pub base: ConfigBase,
     pub extra: Option<ConfigExtra>,
     pub bin: ConfigBin,
+    #[serde(default)]
+    pub aur: ConfigAur,
+    #[serde(default)]
+    pub build: ConfigBuild,
+    #[serde(default)]
+    pub upgrade: ConfigUpgrade,
+    #[serde(default)]
+    pub providers: HashMap<String, String>,
+    #[serde(default)]
+    pub pkgbuild_repos: BTreeMap<String, ConfigPkgbuildRepo>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -25,6 +37,35 @@ pub struct ConfigExtra {
     pub uwu_debug: Option<bool>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConfigAur {
+    pub url: String,
+    pub rpc_url: Option<String>,
+    pub cache_ttl: u64,
+    pub metadata_dump: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConfigBuild {
+    pub chroot: bool,
+    pub local_repo: Option<String>,
+    pub local_repo_name: String,
+    pub keep_archives: usize,
+    pub lint_threshold: Severity,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct ConfigUpgrade {
+    pub ignore: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConfigPkgbuildRepo {
+    pub url: String,
+    #[serde(default = "default_repo_priority")]
+    pub priority: i32,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ConfigBin {
     pub sudo: String,
@@ -36,6 +77,33 @@ impl Default for ConfigBase {
     }
 }
 
+impl Default for ConfigAur {
+    fn default() -> Self {
+        Self {
+            url: "https://aur.archlinux.org/".to_string(),
+            rpc_url: None,
+            cache_ttl: 3600,
+            metadata_dump: false,
+        }
+    }
+}
+
+impl Default for ConfigBuild {
+    fn default() -> Self {
+        Self {
+            chroot: false,
+            local_repo: None,
+            local_repo_name: "amethyst".to_string(),
+            keep_archives: 3,
+            lint_threshold: Severity::High,
+        }
+    }
+}
+
+fn default_repo_priority() -> i32 {
+    1
+}
+
 impl Default for ConfigBin {
     fn default() -> Self {
         Self {
@@ -0,0 +1,156 @@
+use std::collections::HashSet;
+
+use super::{
+    alpm::Alpm,
+    dependencies::Dependency,
+    error::{AppError, AppResult},
+    rpc::PackageInfo,
+};
+
+#[derive(Clone, Debug)]
+pub struct Conflict {
+    /// The package that is about to be installed
+    pub package: String,
+    /// The package it conflicts with
+    pub conflicting: String,
+    pub kind: ConflictKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// Conflicts with an installed package
+    Installed,
+    /// Replaces an installed package
+    Replaces,
+    /// Conflicts with another package of the same transaction
+    Transaction,
+}
+
+/// A package that is checked for conflicts
+struct ConflictCandidate {
+    name: String,
+    version: String,
+    provides: Vec<String>,
+    conflicts: Vec<String>,
+}
+
+impl ConflictCandidate {
+    fn conflicts_with(&self, other: &ConflictCandidate) -> bool {
+        self.name != other.name
+            && self
+                .conflicts
+                .iter()
+                .filter_map(|c| Dependency::try_from_str(c))
+                .any(|c| c.satisfied_by_package(&other.name, &other.version, &other.provides))
+    }
+}
+
+impl From<&PackageInfo> for ConflictCandidate {
+    fn from(pkg: &PackageInfo) -> Self {
+        Self {
+            name: pkg.metadata.name.clone(),
+            version: pkg.metadata.version.clone(),
+            provides: pkg.provides.clone(),
+            conflicts: pkg.conflicts.clone(),
+        }
+    }
+}
+
+impl From<alpm::Package<'_>> for ConflictCandidate {
+    fn from(pkg: alpm::Package) -> Self {
+        Self {
+            name: pkg.name().to_string(),
+            version: pkg.version().to_string(),
+            provides: pkg.provides().iter().map(|p| p.to_string()).collect(),
+            conflicts: pkg.conflicts().iter().map(|c| c.to_string()).collect(),
+        }
+    }
+}
+
+/// Finds all conflicts of the given AUR and repo packages with the installed packages
+/// and with each other
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn find_conflicts(
+    aur_packages: &[&PackageInfo],
+    repo_packages: &[&str],
+) -> AppResult<Vec<Conflict>> {
+    let alpm = Alpm::shared()?;
+    let handle = alpm.handler();
+    let installed: Vec<ConflictCandidate> = handle
+        .localdb()
+        .pkgs()
+        .into_iter()
+        .map(ConflictCandidate::from)
+        .collect();
+    let mut transaction: Vec<ConflictCandidate> = aur_packages
+        .iter()
+        .map(|p| ConflictCandidate::from(*p))
+        .collect();
+    let repo_candidates = repo_packages
+        .iter()
+        .filter_map(|name| handle.syncdbs().find_satisfier(*name))
+        .map(ConflictCandidate::from);
+    transaction.extend(repo_candidates);
+
+    let mut conflicts = Vec::new();
+
+    for pkg in aur_packages {
+        let candidate = ConflictCandidate::from(*pkg);
+        let replaced = pkg
+            .replaces
+            .iter()
+            .filter_map(|r| Dependency::try_from_str(r))
+            .flat_map(|r| {
+                installed
+                    .iter()
+                    .filter(move |i| r.satisfied_by_package(&i.name, &i.version, &i.provides))
+            })
+            .filter(|i| i.name != candidate.name)
+            .map(|i| Conflict {
+                package: candidate.name.clone(),
+                conflicting: i.name.clone(),
+                kind: ConflictKind::Replaces,
+            });
+        conflicts.extend(replaced);
+
+        let installed_conflicts = installed
+            .iter()
+            .filter(|i| candidate.conflicts_with(i) || i.conflicts_with(&candidate))
+            .map(|i| Conflict {
+                package: candidate.name.clone(),
+                conflicting: i.name.clone(),
+                kind: ConflictKind::Installed,
+            });
+        conflicts.extend(installed_conflicts);
+
+        let transaction_conflicts = transaction
+            .iter()
+            .filter(|t| candidate.conflicts_with(t) || t.conflicts_with(&candidate))
+            .map(|t| Conflict {
+                package: candidate.name.clone(),
+                conflicting: t.name.clone(),
+                kind: ConflictKind::Transaction,
+            });
+        conflicts.extend(transaction_conflicts);
+    }
+    let mut seen = HashSet::new();
+    conflicts.retain(|c| seen.insert((c.package.clone(), c.conflicting.clone())));
+    tracing::debug!("Conflicts: {conflicts:?}");
+
+    Ok(conflicts)
+}
+
+/// Returns an error if any of the conflicts can't be resolved by removing installed packages
+pub fn check_transaction_conflicts(conflicts: &[Conflict]) -> AppResult<()> {
+    let unresolvable: Vec<String> = conflicts
+        .iter()
+        .filter(|c| c.kind == ConflictKind::Transaction)
+        .map(|c| format!("{} conflicts with {}", c.package, c.conflicting))
+        .collect();
+
+    if unresolvable.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::ConflictingPackages(unresolvable))
+    }
+}
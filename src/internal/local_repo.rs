@@ -0,0 +1,82 @@
+use std::path::{Path, PathBuf};
+
+use futures::future;
+use tokio::fs;
+
+use super::{commands::ShellCommand, config::Config, error::AppResult};
+
+/// A local pacman repository that built packages are published to
+#[derive(Debug)]
+pub struct LocalRepo {
+    path: PathBuf,
+    name: String,
+}
+
+impl LocalRepo {
+    /// Returns the local repository configured in the `[build]` section if there is one
+    pub fn from_config() -> Option<Self> {
+        let config = &Config::get().build;
+
+        config.local_repo.as_ref().map(|path| Self {
+            path: PathBuf::from(path),
+            name: config.local_repo_name.clone(),
+        })
+    }
+
+    /// The path of the repository database
+    pub fn db_path(&self) -> PathBuf {
+        self.path.join(format!("{}.db.tar.gz", self.name))
+    }
+
+    /// Copies the given archives into the repository and adds them to its database.
+    /// Older builds are kept in the repository directory.
+    #[tracing::instrument(level = "trace")]
+    pub async fn add(&self, archives: &[PathBuf]) -> AppResult<()> {
+        fs::create_dir_all(&self.path).await?;
+        let published = future::try_join_all(archives.iter().map(|a| self.copy_archive(a))).await?;
+
+        ShellCommand::repo_add()
+            .arg(self.db_path())
+            .args(published)
+            .wait_with_output()
+            .await
+            .and_then(|output| {
+                if output.status.success() {
+                    Ok(())
+                } else {
+                    Err(output.stderr.into())
+                }
+            })
+    }
+
+    /// Returns the archive of the given package version if it was published to the repository
+    pub fn find_archive(&self, pkg_name: &str, version: &str) -> AppResult<Option<PathBuf>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let prefix = format!("{pkg_name}-{version}-");
+
+        for entry in std::fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            let is_archive = path.file_name().and_then(|n| n.to_str()).is_some_and(|n| {
+                n.starts_with(&prefix) && n.contains(".pkg.tar") && !n.ends_with(".sig")
+            });
+
+            if is_archive {
+                return Ok(Some(path));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn copy_archive(&self, archive: &Path) -> AppResult<PathBuf> {
+        let file_name = archive
+            .file_name()
+            .ok_or_else(|| format!("Invalid package archive {}", archive.display()))?;
+        let target = self.path.join(file_name);
+        fs::copy(archive, &target).await?;
+
+        Ok(target)
+    }
+}
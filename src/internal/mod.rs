@@ -4,20 +4,30 @@ pub use detect::*;
 pub use sort::*;
 pub use sudoloop::*;
 
+pub mod archives;
+pub mod chroot;
 mod clean;
 pub mod commands;
 pub mod config;
+pub mod conflicts;
 pub mod dependencies;
 mod detect;
+pub mod devel;
 pub mod error;
 pub mod exit_code;
 pub mod fs_utils;
+pub mod ignore;
+pub mod lint;
+pub mod local_repo;
+pub mod pkgbuild_repos;
 pub mod rpc;
 mod sort;
+pub mod srcinfo;
 pub mod structs;
 #[macro_use]
 pub mod utils;
 pub mod alpm;
+pub mod aur_cache;
 mod sudoloop;
 
 #[macro_export]
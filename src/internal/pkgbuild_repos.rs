@@ -0,0 +1,222 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::builder::git::{GitCloneBuilder, GitPullBuilder};
+
+use super::{
+    config::Config,
+    dependencies::Dependency,
+    error::AppResult,
+    rpc::{rpcinfo_many, PackageInfo},
+    srcinfo::SrcInfo,
+    utils::get_cache_dir,
+};
+
+/// The location of a package inside a PKGBUILD repository
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PkgbuildSource {
+    /// The name of the PKGBUILD repository
+    pub repo: String,
+    /// The directory containing the PKGBUILD
+    pub directory: PathBuf,
+}
+
+/// A configured git repository containing one PKGBUILD directory per package base
+#[derive(Debug)]
+struct PkgbuildRepo {
+    name: String,
+    url: String,
+    /// Repositories with a priority above 0 are preferred over the AUR
+    priority: i32,
+    packages: Vec<PackageInfo>,
+}
+
+impl PkgbuildRepo {
+    fn directory(&self) -> PathBuf {
+        get_cache_dir().join("repos").join(&self.name)
+    }
+
+    /// Clones the repository if it's missing, pulls it if requested and reads all packages in it.
+    /// A failed pull keeps the previous clone and packages that can't be read are skipped.
+    #[tracing::instrument(level = "trace", skip(self), fields(name = %self.name))]
+    async fn load(&mut self, pull: bool) -> AppResult<()> {
+        let directory = self.directory();
+
+        if !directory.exists() {
+            GitCloneBuilder::default()
+                .url(&self.url)
+                .directory(&directory)
+                .clone()
+                .await?;
+        } else if pull {
+            if let Err(e) = GitPullBuilder::default().directory(&directory).pull().await {
+                tracing::warn!("Failed to pull the PKGBUILD repository {}: {e}", self.name);
+            }
+        }
+
+        for pkg_dir in package_directories(&directory)? {
+            let srcinfo = match SrcInfo::read_or_generate(&pkg_dir).await {
+                Ok(srcinfo) => srcinfo,
+                Err(e) => {
+                    tracing::warn!("Skipping {}: {e}", pkg_dir.display());
+                    continue;
+                }
+            };
+
+            for mut package in srcinfo.package_infos() {
+                package.pkgbuild_source = Some(PkgbuildSource {
+                    repo: self.name.clone(),
+                    directory: pkg_dir.clone(),
+                });
+                self.packages.push(package);
+            }
+        }
+        tracing::debug!("Found {} packages in {}", self.packages.len(), self.name);
+
+        Ok(())
+    }
+}
+
+/// Returns all direct subdirectories containing a PKGBUILD
+fn package_directories(directory: &Path) -> AppResult<Vec<PathBuf>> {
+    let mut directories = Vec::new();
+
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+
+        if path.join("PKGBUILD").is_file() {
+            directories.push(path);
+        }
+    }
+    directories.sort();
+
+    Ok(directories)
+}
+
+/// Reads all configured repositories on first use and returns them ordered by priority
+async fn repos() -> AppResult<Arc<Vec<PkgbuildRepo>>> {
+    load_repos(false).await
+}
+
+/// Pulls all configured repositories and reads their packages again
+#[tracing::instrument(level = "trace")]
+pub async fn sync() -> AppResult<()> {
+    if !Config::get().pkgbuild_repos.is_empty() {
+        tracing::info!("Updating PKGBUILD repositories");
+        load_repos(true).await?;
+    }
+
+    Ok(())
+}
+
+async fn load_repos(pull: bool) -> AppResult<Arc<Vec<PkgbuildRepo>>> {
+    lazy_static! {
+        static ref REPOS: Mutex<Option<Arc<Vec<PkgbuildRepo>>>> = Mutex::new(None);
+    }
+    // held while loading so that concurrent callers wait for the first load
+    let mut loaded = REPOS.lock().await;
+
+    if let Some(repos) = loaded.as_ref().filter(|_| !pull) {
+        return Ok(repos.clone());
+    }
+    let mut repos: Vec<PkgbuildRepo> = Config::get()
+        .pkgbuild_repos
+        .iter()
+        .map(|(name, repo)| PkgbuildRepo {
+            name: name.clone(),
+            url: repo.url.clone(),
+            priority: repo.priority,
+            packages: Vec::new(),
+        })
+        .collect();
+    repos.sort_by_key(|r| std::cmp::Reverse(r.priority));
+
+    for repo in &mut repos {
+        if let Err(e) = repo.load(pull).await {
+            tracing::warn!("Failed to load the PKGBUILD repository {}: {e}", repo.name);
+        }
+    }
+    let repos = Arc::new(repos);
+    *loaded = Some(repos.clone());
+
+    Ok(repos)
+}
+
+/// Fetches the info for all given packages from the AUR and the PKGBUILD repositories.
+/// Each package is taken from the source with the highest priority that contains it.
+#[tracing::instrument(level = "trace", skip_all)]
+pub async fn info_many<I: IntoIterator<Item = S>, S: AsRef<str>>(
+    pkgs: I,
+) -> AppResult<Vec<PackageInfo>> {
+    let names: Vec<String> = pkgs.into_iter().map(|p| p.as_ref().to_string()).collect();
+    let repos = repos().await?;
+    let find_in_repos = |name: &str, before_aur: bool| {
+        repos
+            .iter()
+            .filter(|r| (r.priority > 0) == before_aur)
+            .flat_map(|r| &r.packages)
+            .find(|p| p.metadata.name == name)
+            .cloned()
+    };
+    let aur_names = names.iter().filter(|n| find_in_repos(n, true).is_none());
+    let mut aur_packages = rpcinfo_many(aur_names).await?;
+
+    let packages = names
+        .iter()
+        .filter_map(|name| {
+            find_in_repos(name, true)
+                .or_else(|| {
+                    let index = aur_packages.iter().position(|p| &p.metadata.name == name)?;
+                    Some(aur_packages.swap_remove(index))
+                })
+                .or_else(|| find_in_repos(name, false))
+        })
+        .collect();
+
+    Ok(packages)
+}
+
+/// Returns all packages of the PKGBUILD repositories providing the given dependency
+pub async fn providers(dependency: &str) -> AppResult<Vec<PackageInfo>> {
+    let providers = repos()
+        .await?
+        .iter()
+        .flat_map(|r| &r.packages)
+        .filter(|p| {
+            p.provides
+                .iter()
+                .filter_map(|p| Dependency::try_from_str(p))
+                .any(|p| p.name == dependency)
+        })
+        .cloned()
+        .collect();
+
+    Ok(providers)
+}
+
+/// Searches the names and descriptions of all packages in the PKGBUILD repositories
+pub async fn search(query: &str) -> AppResult<Vec<PackageInfo>> {
+    let query = query.to_lowercase();
+    let results = repos()
+        .await?
+        .iter()
+        .flat_map(|r| &r.packages)
+        .filter(|p| {
+            p.metadata.name.contains(&query)
+                || p.metadata
+                    .description
+                    .as_ref()
+                    .is_some_and(|d| d.to_lowercase().contains(&query))
+        })
+        .cloned()
+        .collect();
+
+    Ok(results)
+}
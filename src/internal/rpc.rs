@@ -1,38 +1,325 @@
-use aur_rpc::{PackageInfo, PackageMetadata, SearchField};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
 
-use super::error::AppResult;
-pub const URL: &str = "https://aur.archlinux.org/";
+use futures::{StreamExt, TryStreamExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-pub async fn rpcinfo(pkg: &str) -> AppResult<Option<PackageInfo>> {
-    let packages = aur_rpc::info(vec![pkg]).await?;
+use super::{aur_cache, config::Config, error::AppResult, pkgbuild_repos::PkgbuildSource};
 
-    Ok(packages.into_iter().next())
+/// Overrides the configured AUR base URL
+const URL_ENV: &str = "AME_AUR_URL";
+/// Overrides the configured AUR RPC endpoint
+const RPC_URL_ENV: &str = "AME_AUR_RPC_URL";
+
+/// The maximum number of packages requested with a single info call
+const MAX_INFO_ARGS: usize = 150;
+/// The maximum length of the `arg[]` part of an info request. The AUR
+/// rejects requests with overly long URIs.
+const MAX_INFO_ARGS_LENGTH: usize = 4000;
+/// The maximum number of info requests running at the same time
+const MAX_CONCURRENT_REQUESTS: usize = 5;
+
+#[derive(Debug)]
+pub enum RpcError {
+    Http(reqwest::Error),
+    Aur(String),
 }
 
-pub async fn rpcinfo_many(pkgs: &[String]) -> AppResult<Vec<PackageInfo>> {
-    let mut futures = vec![];
-    for pkg in pkgs {
-        futures.push(aur_rpc::info(vec![pkg]));
+impl Display for RpcError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Http(e) => Display::fmt(e, f),
+            Self::Aur(msg) => write!(f, "AUR RPC error: {msg}"),
+        }
+    }
+}
+
+impl From<reqwest::Error> for RpcError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Http(e)
+    }
+}
+
+/// The basic information about a package that is
+/// returned by searches and info requests
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PackageMetadata {
+    pub description: Option<String>,
+    pub first_submitted: u64,
+    #[serde(rename = "ID")]
+    pub id: u32,
+    pub last_modified: u64,
+    pub maintainer: Option<String>,
+    pub name: String,
+    pub num_votes: u32,
+    pub out_of_date: Option<u64>,
+    pub package_base: String,
+    #[serde(rename = "PackageBaseID")]
+    pub package_base_id: u32,
+    pub popularity: f64,
+    #[serde(rename = "URL")]
+    pub url: Option<String>,
+    #[serde(rename = "URLPath")]
+    pub url_path: String,
+    pub version: String,
+}
+
+/// The full information about a package returned by info requests
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PackageInfo {
+    #[serde(flatten)]
+    pub metadata: PackageMetadata,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub license: Vec<String>,
+    #[serde(default)]
+    pub depends: Vec<String>,
+    #[serde(default)]
+    pub make_depends: Vec<String>,
+    #[serde(default)]
+    pub opt_depends: Vec<String>,
+    #[serde(default)]
+    pub check_depends: Vec<String>,
+    #[serde(default)]
+    pub provides: Vec<String>,
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+    #[serde(default)]
+    pub replaces: Vec<String>,
+    /// Where the package comes from if it isn't an AUR package
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pkgbuild_source: Option<PkgbuildSource>,
+}
+
+/// A field to search the AUR by
+#[derive(Debug, Clone, Copy)]
+pub enum SearchField {
+    Name,
+    NameDesc,
+    Maintainer,
+    Depends,
+    MakeDepends,
+    OptDepends,
+    CheckDepends,
+    Provides,
+}
+
+impl SearchField {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::NameDesc => "name-desc",
+            Self::Maintainer => "maintainer",
+            Self::Depends => "depends",
+            Self::MakeDepends => "makedepends",
+            Self::OptDepends => "optdepends",
+            Self::CheckDepends => "checkdepends",
+            Self::Provides => "provides",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    #[serde(default = "Vec::new")]
+    results: Vec<T>,
+    error: Option<String>,
+}
+
+/// Returns the base URL of the AUR web interface and git repositories
+pub fn aur_url() -> String {
+    let url = std::env::var(URL_ENV).unwrap_or_else(|_| Config::get().aur.url.clone());
+
+    url.trim_end_matches('/').to_string()
+}
+
+/// Returns the URL of the git repository of the given package base
+pub fn aur_git_url(package_base: &str) -> String {
+    format!("{}/{package_base}.git", aur_url())
+}
+
+/// Returns the RPC endpoint. Defaults to `<AUR URL>/rpc/`
+fn rpc_url() -> String {
+    std::env::var(RPC_URL_ENV)
+        .ok()
+        .or_else(|| Config::get().aur.rpc_url.clone())
+        .unwrap_or_else(|| format!("{}/rpc/", aur_url()))
+}
+
+#[tracing::instrument(level = "debug")]
+async fn call_rpc<T: DeserializeOwned>(args: &[(&str, &str)]) -> Result<Vec<T>, RpcError> {
+    let response: RpcResponse<T> = reqwest::Client::new()
+        .get(rpc_url())
+        .query(&[("v", "5")])
+        .query(args)
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    if let Some(error) = response.error {
+        Err(RpcError::Aur(error))
+    } else {
+        Ok(response.results)
+    }
+}
+
+async fn info(pkgs: Vec<String>) -> Result<Vec<PackageInfo>, RpcError> {
+    let mut args = vec![("type", "info")];
+    args.extend(pkgs.iter().map(|p| ("arg[]", p.as_str())));
+
+    call_rpc(&args).await
+}
+
+/// Fetches the info for all given packages with as few requests as possible.
+/// Packages are taken from the metadata dump or the info cache when possible.
+/// The remaining names are split into chunks that are requested concurrently.
+#[tracing::instrument(level = "trace", skip_all)]
+pub async fn rpcinfo_many<I: IntoIterator<Item = S>, S: AsRef<str>>(
+    pkgs: I,
+) -> AppResult<Vec<PackageInfo>> {
+    let mut names: Vec<String> = pkgs.into_iter().map(|p| p.as_ref().to_string()).collect();
+    let mut packages = Vec::new();
+
+    if let Some(dump) = metadata_dump().await {
+        // packages added after the dump was created are fetched from the RPC
+        names.retain(|name| match dump.get(name) {
+            Some(package) => {
+                packages.push(package.clone());
+                false
+            }
+            None => true,
+        });
     }
+    let (cached, missing) = aur_cache::cached_info(names);
+    packages.extend(cached);
+
+    if missing.is_empty() {
+        return Ok(packages);
+    }
+    let chunks = chunk_package_names(missing);
+    tracing::debug!("Fetching package info in {} requests", chunks.len());
+
+    let results: Vec<Vec<PackageInfo>> = futures::stream::iter(chunks)
+        .map(info)
+        .buffer_unordered(MAX_CONCURRENT_REQUESTS)
+        .try_collect()
+        .await?;
+    let fetched: Vec<PackageInfo> = results.into_iter().flatten().collect();
+    aur_cache::store_info(&fetched);
+    packages.extend(fetched);
+
+    Ok(packages)
+}
+
+/// Splits the given package names into chunks that fit into a single info request
+fn chunk_package_names<I: IntoIterator<Item = S>, S: AsRef<str>>(pkgs: I) -> Vec<Vec<String>> {
+    let mut chunks = Vec::new();
+    let mut current_chunk: Vec<String> = Vec::new();
+    let mut current_length = 0;
+
+    for pkg in pkgs {
+        let pkg = pkg.as_ref();
+        // `&arg[]=` is encoded as `&arg%5B%5D=`
+        let arg_length = encoded_length(pkg) + 11;
 
-    let mut results = vec![];
-    for future in futures {
-        let mut result = future.await?;
-        results.append(&mut result);
+        if !current_chunk.is_empty()
+            && (current_chunk.len() >= MAX_INFO_ARGS
+                || current_length + arg_length > MAX_INFO_ARGS_LENGTH)
+        {
+            chunks.push(std::mem::take(&mut current_chunk));
+            current_length = 0;
+        }
+        current_length += arg_length;
+        current_chunk.push(pkg.to_string());
     }
+    if !current_chunk.is_empty() {
+        chunks.push(current_chunk);
+    }
+
+    chunks
+}
 
-    Ok(results)
+/// Returns the length of the given query value once it's form url encoded
+fn encoded_length(value: &str) -> usize {
+    value
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"*-._ ".contains(&b) {
+                1
+            } else {
+                3
+            }
+        })
+        .sum()
 }
 
 pub async fn rpcsearch(
     query: String,
     by_field: Option<SearchField>,
 ) -> AppResult<Vec<PackageMetadata>> {
-    let search_results = if let Some(field) = by_field {
-        aur_rpc::search_by(field, query).await?
-    } else {
-        aur_rpc::search(query).await?
-    };
+    let field = by_field.unwrap_or(SearchField::Name);
+
+    if let Some(dump) = metadata_dump().await {
+        return Ok(dump.search(&query, field));
+    }
+    let search_results =
+        call_rpc(&[("type", "search"), ("by", field.as_str()), ("arg", &query)]).await?;
 
     Ok(search_results)
 }
+
+/// Returns the metadata dump if it's enabled. The RPC is used when it can't be loaded.
+async fn metadata_dump() -> Option<Arc<aur_cache::MetadataDump>> {
+    aur_cache::metadata_dump().await.unwrap_or_else(|e| {
+        tracing::warn!("Failed to load the AUR metadata dump, using the RPC instead: {e}");
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_length_counts_percent_encoding() {
+        assert_eq!(encoded_length("amethyst"), 8);
+        assert_eq!(encoded_length("gtk+"), 6);
+        assert_eq!(encoded_length("lib32-gcc@12"), 14);
+    }
+
+    #[test]
+    fn chunks_by_package_count() {
+        let names: Vec<String> = (0..MAX_INFO_ARGS * 2 + 1)
+            .map(|i| format!("pkg{i}"))
+            .collect();
+        let chunks = chunk_package_names(&names);
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].len(), MAX_INFO_ARGS);
+        assert_eq!(chunks[2].len(), 1);
+        assert_eq!(chunks.concat(), names);
+    }
+
+    #[test]
+    fn chunks_by_encoded_length() {
+        let name = "+".repeat(100);
+        let names = vec![name; 20];
+        let chunks = chunk_package_names(&names);
+
+        for chunk in &chunks {
+            let length: usize = chunk.iter().map(|p| encoded_length(p) + 11).sum();
+            assert!(length <= MAX_INFO_ARGS_LENGTH);
+        }
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks.concat(), names);
+    }
+
+    #[test]
+    fn chunks_nothing_for_no_packages() {
+        assert!(chunk_package_names(Vec::<String>::new()).is_empty());
+    }
+}
@@ -1,6 +1,9 @@
+use crate::crash;
 use crate::internal::alpm::{Alpm, PackageFrom};
+use crate::internal::error::{AppError, SilentUnwrap};
+use crate::internal::exit_code::AppExitCode;
 use crate::internal::structs::Sorted;
-use crate::internal::{clean, rpc};
+use crate::internal::{clean, pkgbuild_repos};
 use crate::Options;
 
 #[tracing::instrument(level = "trace")]
@@ -10,10 +13,19 @@ pub async fn sort(input: &[String], options: Options) -> Sorted {
     let mut missing_packages: Vec<String> = vec![];
 
     let packages = clean(input);
-    let alpm = Alpm::new().unwrap();
 
     tracing::debug!("Sorting: {:?}", packages.join(" "));
-    let aur_query = rpc::rpcinfo_many(&packages).await.unwrap();
+    let aur_query = pkgbuild_repos::info_many(&packages)
+        .await
+        .unwrap_or_else(|e| {
+            crash!(
+                AppExitCode::RpcError,
+                "Failed to fetch package information: {e}"
+            )
+        });
+    let alpm = Alpm::shared()
+        .map_err(AppError::from)
+        .silent_unwrap(AppExitCode::Other);
 
     for package in packages {
         let package_result = alpm.load(PackageFrom::SyncDb(package.clone()));
@@ -22,7 +34,7 @@ pub async fn sort(input: &[String], options: Options) -> Sorted {
             tracing::debug!("{} found in repos", package);
             repo_packages.push(package);
         } else if aur_query.iter().any(|p| p.metadata.name == package) {
-            tracing::debug!("{} found in AUR", package);
+            tracing::debug!("{} found in AUR or PKGBUILD repositories", package);
             aur_packages.push(package.to_string());
         } else {
             tracing::debug!("{} not found", package);
@@ -0,0 +1,377 @@
+use std::{fs, path::Path};
+
+use super::{
+    commands::ShellCommand,
+    error::{AppError, AppResult},
+    rpc::{PackageInfo, PackageMetadata},
+    utils::{get_cache_dir, stable_hash},
+};
+
+/// The parsed `.SRCINFO` of a package base
+#[derive(Clone, Debug)]
+pub struct SrcInfo {
+    base: Section,
+    packages: Vec<Section>,
+}
+
+/// The `pkgbase` section or a `pkgname` section of a .SRCINFO
+#[derive(Clone, Debug)]
+struct Section {
+    name: String,
+    fields: Vec<(String, String)>,
+}
+
+impl Section {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            fields: Vec::new(),
+        }
+    }
+
+    fn has(&self, key: &str) -> bool {
+        self.fields.iter().any(|(k, _)| k == key)
+    }
+
+    fn values<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        self.fields
+            .iter()
+            .filter(move |(k, v)| k == key && !v.is_empty())
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn value<'a>(&'a self, key: &'a str) -> Option<&'a str> {
+        self.values(key).next()
+    }
+}
+
+impl SrcInfo {
+    /// Reads the .SRCINFO from the given package directory
+    pub fn read(pkg_dir: &Path) -> AppResult<Self> {
+        let content = fs::read_to_string(pkg_dir.join(".SRCINFO"))?;
+
+        Self::parse(&content)
+    }
+
+    /// Reads the .SRCINFO from the given package directory. If it's missing or older than
+    /// the PKGBUILD it's generated from the PKGBUILD instead. Generated files are kept in the
+    /// cache directory so that directories and clones of other sources are never modified.
+    #[tracing::instrument(level = "trace")]
+    pub async fn read_or_generate(pkg_dir: &Path) -> AppResult<Self> {
+        let pkgbuild_modified = fs::metadata(pkg_dir.join("PKGBUILD"))?.modified()?;
+        let is_current = |path: &Path| {
+            fs::metadata(path)
+                .and_then(|m| m.modified())
+                .is_ok_and(|modified| modified >= pkgbuild_modified)
+        };
+
+        if is_current(&pkg_dir.join(".SRCINFO")) {
+            return Self::read(pkg_dir);
+        }
+        let pkg_dir = pkg_dir.canonicalize()?;
+        let cache_dir = get_cache_dir().join("srcinfo");
+        let cached_path = cache_dir.join(stable_hash(&pkg_dir.to_string_lossy()));
+
+        if is_current(&cached_path) {
+            return Self::parse(&fs::read_to_string(cached_path)?);
+        }
+        tracing::debug!("Generating .SRCINFO for {pkg_dir:?}");
+        let output = ShellCommand::makepkg()
+            .working_dir(&pkg_dir)
+            .arg("--printsrcinfo")
+            .wait_with_output()
+            .await?;
+
+        if !output.status.success() {
+            return Err(AppError::MakePkg(output.stderr));
+        }
+        let srcinfo = Self::parse(&output.stdout)?;
+        fs::create_dir_all(cache_dir)?;
+        fs::write(cached_path, &output.stdout)?;
+
+        Ok(srcinfo)
+    }
+
+    pub fn parse(content: &str) -> AppResult<Self> {
+        let mut base: Option<Section> = None;
+        let mut packages: Vec<Section> = Vec::new();
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| AppError::from(format!("Invalid .SRCINFO line: {line}")))?;
+
+            if key == "pkgbase" {
+                base = Some(Section::new(value));
+            } else if key == "pkgname" {
+                packages.push(Section::new(value));
+            } else if let Some(section) = packages.last_mut().or(base.as_mut()) {
+                section.fields.push((key.to_string(), value.to_string()));
+            } else {
+                return Err(missing_pkgbase());
+            }
+        }
+
+        Ok(Self {
+            base: base.ok_or_else(missing_pkgbase)?,
+            packages,
+        })
+    }
+
+    /// Returns the full version in the form `[epoch:]pkgver-pkgrel`
+    pub fn version(&self) -> String {
+        let pkgver = self.base.value("pkgver").unwrap_or_default();
+        let pkgrel = self.base.value("pkgrel").unwrap_or_default();
+
+        match self.base.value("epoch") {
+            Some(epoch) => format!("{epoch}:{pkgver}-{pkgrel}"),
+            None => format!("{pkgver}-{pkgrel}"),
+        }
+    }
+
+    /// Returns the names of all packages built from the package base
+    pub fn package_names(&self) -> Vec<&str> {
+        self.packages.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    pub fn depends(&self, pkg_name: &str) -> Vec<String> {
+        self.package_values(Some(pkg_name), "depends")
+    }
+
+    pub fn make_depends(&self) -> Vec<String> {
+        self.package_values(None, "makedepends")
+    }
+
+    pub fn check_depends(&self) -> Vec<String> {
+        self.package_values(None, "checkdepends")
+    }
+
+    pub fn opt_depends(&self, pkg_name: &str) -> Vec<String> {
+        self.package_values(Some(pkg_name), "optdepends")
+    }
+
+    pub fn provides(&self, pkg_name: &str) -> Vec<String> {
+        self.package_values(Some(pkg_name), "provides")
+    }
+
+    pub fn conflicts(&self, pkg_name: &str) -> Vec<String> {
+        self.package_values(Some(pkg_name), "conflicts")
+    }
+
+    pub fn replaces(&self, pkg_name: &str) -> Vec<String> {
+        self.package_values(Some(pkg_name), "replaces")
+    }
+
+    /// Returns the sources of all architectures
+    pub fn sources(&self) -> Vec<&str> {
+        self.base
+            .fields
+            .iter()
+            .filter(|(key, value)| {
+                (key == "source" || key.starts_with("source_")) && !value.is_empty()
+            })
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    /// Creates the package information of all packages built from the package base
+    pub fn package_infos(&self) -> Vec<PackageInfo> {
+        self.package_names()
+            .into_iter()
+            .map(|name| {
+                let metadata = PackageMetadata {
+                    description: self.package_values(Some(name), "pkgdesc").pop(),
+                    first_submitted: 0,
+                    id: 0,
+                    last_modified: 0,
+                    maintainer: None,
+                    name: name.to_string(),
+                    num_votes: 0,
+                    out_of_date: None,
+                    package_base: self.base.name.clone(),
+                    package_base_id: 0,
+                    popularity: 0.0,
+                    url: self.package_values(Some(name), "url").pop(),
+                    url_path: String::new(),
+                    version: self.version(),
+                };
+                let mut info = PackageInfo {
+                    metadata,
+                    keywords: Vec::new(),
+                    license: self.package_values(Some(name), "license"),
+                    depends: Vec::new(),
+                    make_depends: Vec::new(),
+                    opt_depends: Vec::new(),
+                    check_depends: Vec::new(),
+                    provides: Vec::new(),
+                    conflicts: Vec::new(),
+                    replaces: Vec::new(),
+                    pkgbuild_source: None,
+                };
+                self.apply_to(&mut info);
+
+                info
+            })
+            .collect()
+    }
+
+    /// Replaces the version and dependency information of the given package
+    /// with the one of the .SRCINFO
+    pub fn apply_to(&self, package: &mut PackageInfo) {
+        let name = package.metadata.name.clone();
+        package.metadata.version = self.version();
+        package.depends = self.depends(&name);
+        package.make_depends = self.make_depends();
+        package.check_depends = self.check_depends();
+        package.opt_depends = self.opt_depends(&name);
+        package.provides = self.provides(&name);
+        package.conflicts = self.conflicts(&name);
+        package.replaces = self.replaces(&name);
+    }
+
+    /// Returns the values of the generic and the architecture specific key.
+    /// Each key set in the package section overrides the one of the package base.
+    fn package_values(&self, pkg_name: Option<&str>, key: &str) -> Vec<String> {
+        let package = pkg_name.and_then(|name| self.packages.iter().find(|p| p.name == name));
+        let arch_key = format!("{key}_{}", arch());
+
+        [key, arch_key.as_str()]
+            .into_iter()
+            .flat_map(|key| {
+                package
+                    .filter(|p| p.has(key))
+                    .unwrap_or(&self.base)
+                    .values(key)
+            })
+            .map(String::from)
+            .collect()
+    }
+}
+
+fn missing_pkgbase() -> AppError {
+    AppError::from("Invalid .SRCINFO: missing pkgbase")
+}
+
+/// Returns the architecture in the naming used by pacman
+fn arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86" => "i686",
+        arch => arch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRCINFO: &str = "\
+pkgbase = foo
+\tpkgdesc = Foo tools
+\tpkgver = 1.2.3
+\tpkgrel = 2
+\tepoch = 1
+\turl = https://example.com/foo
+\tarch = x86_64
+\tlicense = MIT
+\tmakedepends = cmake
+\tcheckdepends = python
+\tdepends = glibc
+\tdepends_x86_64 = lib32-glibc
+\tsource = foo-1.2.3.tar.gz::https://example.com/foo-1.2.3.tar.gz
+\tsource_x86_64 = https://example.com/foo-x86_64.patch
+\tsha256sums = SKIP
+
+pkgname = foo
+\tdepends = glibc
+\tdepends = libfoo
+
+pkgname = libfoo
+\tpkgdesc = Foo library
+\tdepends =
+\tprovides = libfoo.so=1-64
+";
+
+    #[test]
+    fn parses_sections() {
+        let srcinfo = SrcInfo::parse(SRCINFO).unwrap();
+
+        assert_eq!(srcinfo.base.name, "foo");
+        assert_eq!(srcinfo.package_names(), ["foo", "libfoo"]);
+        assert_eq!(srcinfo.version(), "1:1.2.3-2");
+        assert_eq!(
+            srcinfo.sources(),
+            [
+                "foo-1.2.3.tar.gz::https://example.com/foo-1.2.3.tar.gz",
+                "https://example.com/foo-x86_64.patch"
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_content() {
+        assert!(SrcInfo::parse("pkgname = foo\n").is_err());
+        assert!(SrcInfo::parse("pkgbase = foo\n\tpkgver\n").is_err());
+        assert!(SrcInfo::parse("").is_err());
+    }
+
+    #[test]
+    fn package_sections_override_the_base() {
+        let srcinfo = SrcInfo::parse(SRCINFO).unwrap();
+
+        // architecture specific values are overridden separately like in makepkg
+        let arch_depends: &[&str] = if arch() == "x86_64" {
+            &["lib32-glibc"]
+        } else {
+            &[]
+        };
+        assert_eq!(
+            srcinfo.depends("foo"),
+            [&["glibc", "libfoo"], arch_depends].concat()
+        );
+        // an empty value clears the base value
+        assert_eq!(srcinfo.depends("libfoo"), arch_depends);
+        assert_eq!(srcinfo.provides("libfoo"), ["libfoo.so=1-64"]);
+        assert_eq!(
+            srcinfo.package_values(Some("libfoo"), "pkgdesc"),
+            ["Foo library"]
+        );
+        assert_eq!(
+            srcinfo.package_values(Some("foo"), "pkgdesc"),
+            ["Foo tools"]
+        );
+    }
+
+    #[test]
+    fn package_values_include_the_architecture() {
+        let srcinfo = SrcInfo::parse(SRCINFO).unwrap();
+        let expected: Vec<&str> = if arch() == "x86_64" {
+            vec!["glibc", "lib32-glibc"]
+        } else {
+            vec!["glibc"]
+        };
+
+        assert_eq!(srcinfo.package_values(None, "depends"), expected);
+        assert_eq!(srcinfo.package_values(Some("bar"), "depends"), expected);
+        assert_eq!(srcinfo.make_depends(), ["cmake"]);
+        assert_eq!(srcinfo.check_depends(), ["python"]);
+    }
+
+    #[test]
+    fn creates_package_infos() {
+        let infos = SrcInfo::parse(SRCINFO).unwrap().package_infos();
+
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[1].metadata.name, "libfoo");
+        assert_eq!(infos[1].metadata.package_base, "foo");
+        assert_eq!(infos[1].metadata.version, "1:1.2.3-2");
+        assert_eq!(
+            infos[1].metadata.description.as_deref(),
+            Some("Foo library")
+        );
+        assert_eq!(infos[1].license, ["MIT"]);
+    }
+}
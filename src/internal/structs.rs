@@ -15,10 +15,14 @@ impl Sorted {
     }
 }
 
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug, Copy, serde::Deserialize, serde::Serialize)]
 /// Options to be passed down to internal functions
 pub struct Options {
     pub noconfirm: bool,
     pub asdeps: bool,
     pub upgrade: bool,
+    pub keep_going: bool,
+    pub chroot: bool,
+    pub dry_run: bool,
+    pub json: bool,
 }
@@ -71,6 +71,17 @@ fn create_if_not_exist(dir: &Path) -> &Path {
     dir
 }
 
+/// Returns a hash of the given value that doesn't change between runs
+/// so that it can be used to name files in the cache directory
+pub fn stable_hash(value: &str) -> String {
+    // 64 bit FNV-1a
+    let hash = value.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+
+    format!("{hash:016x}")
+}
+
 pub fn wrap_text<S: AsRef<str>>(s: S, padding: usize) -> Vec<String> {
     let subsequent_padding = " ".repeat(padding);
     let opts =
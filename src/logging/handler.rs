@@ -23,6 +23,7 @@ pub struct LogHandler {
     level: Arc<RwLock<Verbosity>>,
     output_type: Arc<RwLock<OutputType>>,
     uwu_enabled: Arc<AtomicBool>,
+    json_enabled: Arc<AtomicBool>,
 }
 
 impl Default for LogHandler {
@@ -31,6 +32,7 @@ impl Default for LogHandler {
             level: Arc::new(RwLock::new(Verbosity::Info)),
             output_type: Arc::new(RwLock::new(OutputType::Stderr)),
             uwu_enabled: Arc::new(AtomicBool::new(false)),
+            json_enabled: Arc::new(AtomicBool::new(false)),
         }
     }
 }
@@ -120,7 +122,12 @@ impl LogHandler {
     }
 
     pub fn reset_output_type(&self) {
-        self.set_output_type(OutputType::Stdout);
+        // stdout is reserved for machine readable output in json mode
+        if self.json_enabled.load(std::sync::atomic::Ordering::Relaxed) {
+            self.set_output_type(OutputType::Stderr);
+        } else {
+            self.set_output_type(OutputType::Stdout);
+        }
     }
 
     #[must_use]
@@ -129,6 +136,13 @@ impl LogHandler {
         let mut old_output_type = OutputType::Stdout;
         mem::swap(&mut *output_type, &mut old_output_type);
 
+        // progress bars would keep redrawing over interactive prompts
+        match &old_output_type {
+            OutputType::MultiProgress(mp) => mp.set_draw_target(ProgressDrawTarget::hidden()),
+            OutputType::Progress(p) => p.set_draw_target(ProgressDrawTarget::hidden()),
+            _ => {}
+        }
+
         (*output_type) = OutputType::Buffer {
             buffer: Arc::new(Mutex::new(Vec::new())),
             suspended: Box::new(old_output_type),
@@ -145,6 +159,13 @@ impl LogHandler {
             mem::swap(&mut *output_type, &mut old_output_type);
 
             if let OutputType::Buffer { buffer, suspended } = old_output_type {
+                match &*suspended {
+                    OutputType::MultiProgress(mp) => {
+                        mp.set_draw_target(ProgressDrawTarget::stderr())
+                    }
+                    OutputType::Progress(p) => p.set_draw_target(ProgressDrawTarget::stderr()),
+                    _ => {}
+                }
                 (*output_type) = *suspended;
                 buffered = mem::take(&mut *buffer.lock());
             }
@@ -204,6 +225,12 @@ impl LogHandler {
             .store(enabled, std::sync::atomic::Ordering::Relaxed);
     }
 
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn set_json_enabled(&self, enabled: bool) {
+        self.json_enabled
+            .store(enabled, std::sync::atomic::Ordering::Relaxed);
+    }
+
     pub(crate) fn is_loggable(&self, level: Verbosity) -> bool {
         (*self.level.read()) >= level
     }
@@ -1,14 +1,22 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
-use aur_rpc::PackageInfo;
 use console::Alignment;
 use crossterm::style::Stylize;
+use serde_json::Value;
 
-use crate::{builder::pacman::PacmanQueryBuilder, internal::dependencies::DependencyInformation};
+use crate::crash;
+use crate::internal::{
+    alpm::{Alpm, PackageFrom},
+    conflicts::{Conflict, ConflictKind},
+    dependencies::DependencyInformation,
+    exit_code::AppExitCode,
+    lint::{Finding, Severity},
+    rpc::PackageInfo,
+};
 
 use super::get_logger;
 
-pub async fn print_dependency_list(dependencies: &[DependencyInformation]) -> bool {
+pub fn print_dependency_list(dependencies: &[DependencyInformation]) -> bool {
     let (mut deps_repo, mut makedeps_repo, deps_aur, makedeps_aur) = dependencies
         .iter()
         .map(|d| {
@@ -42,7 +50,7 @@ pub async fn print_dependency_list(dependencies: &[DependencyInformation]) -> bo
     }
     if !deps_aur.is_empty() {
         tracing::info!("AUR dependencies");
-        print_aur_package_list(&deps_aur).await;
+        print_aur_package_list(&deps_aur);
         empty = false;
         get_logger().print_newline();
     }
@@ -56,7 +64,7 @@ pub async fn print_dependency_list(dependencies: &[DependencyInformation]) -> bo
 
     if !makedeps_aur.is_empty() {
         tracing::info!("AUR make dependencies");
-        print_aur_package_list(&makedeps_aur).await;
+        print_aur_package_list(&makedeps_aur);
         empty = false;
         get_logger().print_newline();
     }
@@ -64,19 +72,13 @@ pub async fn print_dependency_list(dependencies: &[DependencyInformation]) -> bo
     empty
 }
 
-pub async fn print_aur_package_list(packages: &[&PackageInfo]) -> bool {
-    let pkgs = packages
+pub fn print_aur_package_list(packages: &[&PackageInfo]) -> bool {
+    let alpm = Alpm::shared().unwrap();
+    let installed = packages
         .iter()
         .map(|p| p.metadata.name.clone())
+        .filter(|name| alpm.load(PackageFrom::LocalDb(name.clone())).is_ok())
         .collect::<HashSet<_>>();
-    let installed = PacmanQueryBuilder::all()
-        .query_with_output()
-        .await
-        .unwrap()
-        .into_iter()
-        .filter(|p| pkgs.contains(&p.name))
-        .map(|p| (p.name.clone(), p))
-        .collect::<HashMap<_, _>>();
 
     get_logger().print_list(
         packages.iter().map(|pkg| {
@@ -85,7 +87,7 @@ pub async fn print_aur_package_list(packages: &[&PackageInfo]) -> bool {
                 console::pad_str(&pkg.metadata.name, 30, Alignment::Left, Some("...")).bold(),
                 pkg.metadata.version.clone().dim(),
                 pkg.metadata.num_votes,
-                if installed.contains_key(&pkg.metadata.name) {
+                if installed.contains(&pkg.metadata.name) {
                     "(Installed)"
                 } else {
                     ""
@@ -100,3 +102,68 @@ pub async fn print_aur_package_list(packages: &[&PackageInfo]) -> bool {
 
     !installed.is_empty()
 }
+
+pub fn print_conflict_list(conflicts: &[Conflict]) {
+    if conflicts.is_empty() {
+        return;
+    }
+    tracing::info!("Conflicts");
+    get_logger().print_list(
+        conflicts.iter().map(|c| {
+            let reason = match c.kind {
+                ConflictKind::Installed => "conflicts with installed",
+                ConflictKind::Replaces => "replaces installed",
+                ConflictKind::Transaction => "conflicts with",
+            };
+            format!(
+                "{} {} {}",
+                console::pad_str(&c.package, 30, Alignment::Left, Some("...")).bold(),
+                reason.dim(),
+                c.conflicting.clone().red()
+            )
+        }),
+        "\n",
+        2,
+    );
+    get_logger().print_newline();
+}
+
+pub fn print_lint_findings(pkg_name: &str, findings: &[Finding]) {
+    if findings.is_empty() {
+        return;
+    }
+    tracing::warn!("Found risky patterns in {pkg_name}");
+    get_logger().print_list(
+        findings.iter().map(|f| {
+            let severity =
+                console::pad_str(&f.severity.to_string(), 8, Alignment::Left, None).to_string();
+            let severity = match f.severity {
+                Severity::Low => severity.dim(),
+                Severity::Medium => severity.yellow(),
+                Severity::High => severity.red(),
+                Severity::Critical => severity.red().bold(),
+            };
+            let location = if f.line > 0 {
+                format!("{}:{}", f.file, f.line)
+            } else {
+                f.file.clone()
+            };
+            format!("{severity} {} {}", location.bold(), f.message)
+        }),
+        "\n",
+        2,
+    );
+    get_logger().print_newline();
+}
+
+/// Writes a JSON document of the given kind as a single line to stdout.
+/// Logs are written to stderr so that the output can be parsed line by line.
+pub fn print_json(kind: &str, mut document: Value) {
+    if let Value::Object(map) = &mut document {
+        map.insert(String::from("kind"), Value::from(kind));
+    }
+    match serde_json::to_string(&document) {
+        Ok(json) => println!("{json}"),
+        Err(e) => crash!(AppExitCode::Other, "Failed to serialize the output: {e}"),
+    }
+}
@@ -8,13 +8,17 @@ use internal::error::SilentUnwrap;
 
 use crate::args::{InstallArgs, Operation, QueryArgs, RemoveArgs};
 use crate::interact::page_string;
+use crate::internal::config::Config;
 use crate::internal::exit_code::AppExitCode;
-use crate::internal::{sort, start_sudoloop, structs::Options};
+use crate::internal::{aur_cache, pkgbuild_repos, sort, start_sudoloop, structs::Options};
 use crate::logging::get_logger;
+use crate::logging::output::print_json;
 use crate::logging::Printable;
+use crate::operations::PackageSource;
 
 use clap_complete::{Generator, Shell};
 use clap_complete_fig::Fig;
+use serde_json::json;
 
 use std::str::FromStr;
 
@@ -35,6 +39,7 @@ async fn main() {
 
     let args: Args = Args::parse();
     init_logger(args.verbose.into());
+    get_logger().set_json_enabled(args.json);
 
     let noconfirm = args.no_confirm;
 
@@ -42,9 +47,21 @@ async fn main() {
         noconfirm,
         asdeps: false,
         upgrade: false,
+        keep_going: args.keep_going,
+        chroot: args.chroot || Config::get().build.chroot,
+        dry_run: args.dry_run,
+        json: args.json,
     };
 
-    if args.sudoloop {
+    if args.refresh {
+        aur_cache::force_refresh();
+
+        if let Err(e) = pkgbuild_repos::sync().await {
+            tracing::warn!("Failed to update the PKGBUILD repositories: {e}");
+        }
+    }
+
+    if args.sudoloop && !args.dry_run {
         start_sudoloop().await;
     }
 
@@ -59,11 +76,17 @@ async fn main() {
 
             cmd_install(args, options).await;
         }
-        Operation::Query(query_args) => cmd_query(query_args).await,
+        Operation::Query(query_args) => cmd_query(query_args, options).await,
+        Operation::Info(info_args) => operations::info(info_args, options).await,
         Operation::Upgrade(upgrade_args) => {
             tracing::info!("Performing system upgrade");
             operations::upgrade(upgrade_args, options).await;
         }
+        Operation::Rollback(rollback_args) => operations::rollback(rollback_args, options).await,
+        Operation::Resume => {
+            tracing::info!("Resuming unfinished installation");
+            operations::aur_resume().await;
+        }
         Operation::Clean => {
             tracing::info!("Removing orphaned packages");
             operations::clean(options).await;
@@ -88,13 +111,18 @@ async fn cmd_install(args: InstallArgs, options: Options) {
                 return;
             }
 
+            let sources = local_sources(&args);
+
             if args.aur && !args.repo {
-                operations::aur_install(packages.to_vec(), options).await;
+                operations::aur_install_with_sources(packages.to_vec(), sources, options).await;
                 return;
             }
 
             if both {
                 let sorted = sort(packages, options).await;
+                if options.json {
+                    print_json("sorted", json!(sorted));
+                }
                 if !sorted.nf.is_empty() {
                     crash!(
                         AppExitCode::PacmanError,
@@ -105,14 +133,31 @@ async fn cmd_install(args: InstallArgs, options: Options) {
                 if !sorted.repo.is_empty() {
                     operations::install(sorted.repo, options).await;
                 }
-                if !sorted.aur.is_empty() {
-                    operations::aur_install(sorted.aur, options).await;
+                if !sorted.aur.is_empty() || !sources.is_empty() {
+                    operations::aur_install_with_sources(sorted.aur, sources, options).await;
                 }
             }
         }
     };
 }
 
+/// Collects the PKGBUILD directories and git repositories passed to install
+fn local_sources(args: &InstallArgs) -> Vec<PackageSource> {
+    let directories = args.pkgbuild.iter().map(|dir| {
+        let dir = std::fs::canonicalize(dir).unwrap_or_else(|e| {
+            crash!(
+                AppExitCode::Other,
+                "Invalid PKGBUILD directory {}: {e}",
+                dir.display()
+            )
+        });
+        PackageSource::Directory(dir)
+    });
+    let repositories = args.git.iter().cloned().map(PackageSource::Git);
+
+    directories.chain(repositories).collect()
+}
+
 #[tracing::instrument(level = "trace")]
 async fn cmd_remove(args: RemoveArgs, options: Options) {
     let packages = args.packages;
@@ -138,20 +183,25 @@ async fn cmd_search(args: InstallArgs, options: Options) {
         results.extend(res);
     }
 
-    if results.is_empty() {
+    results.sort_by(|a, b| {
+        let a_score = a.score(&query_string);
+        let b_score = b.score(&query_string);
+
+        b_score
+            .partial_cmp(&a_score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    if options.json {
+        print_json(
+            "search",
+            json!({ "query": query_string, "results": results }),
+        );
+    } else if results.is_empty() {
         tracing::info!("No results found");
     } else {
         tracing::info!("Results:");
 
-        results.sort_by(|a, b| {
-            let a_score = a.score(&query_string);
-            let b_score = b.score(&query_string);
-
-            b_score
-                .partial_cmp(&a_score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-
         let list: Vec<String> = results.iter().map(|x| x.to_print_string()).collect();
         get_logger().print_list(&list, "\n", 0);
 
@@ -162,9 +212,14 @@ async fn cmd_search(args: InstallArgs, options: Options) {
 }
 
 #[tracing::instrument(level = "trace")]
-async fn cmd_query(args: QueryArgs) {
+async fn cmd_query(args: QueryArgs, options: Options) {
     let both = !args.aur && !args.repo && args.info.is_none();
 
+    if options.json {
+        cmd_query_json(args, both).await;
+        return;
+    }
+
     if args.repo {
         tracing::info!("Installed Repo Packages: ");
         PacmanQueryBuilder::native()
@@ -201,6 +256,36 @@ async fn cmd_query(args: QueryArgs) {
     }
 }
 
+#[tracing::instrument(level = "trace")]
+async fn cmd_query_json(args: QueryArgs, both: bool) {
+    let mut queries = Vec::new();
+
+    if args.repo {
+        queries.push(PacmanQueryBuilder::native());
+    }
+    if args.aur {
+        queries.push(PacmanQueryBuilder::foreign());
+    }
+    if both {
+        queries.push(PacmanQueryBuilder::all());
+    }
+    if let Some(info) = args.info {
+        queries.push(PacmanQueryBuilder::all().package(info));
+    }
+    let mut packages = Vec::new();
+
+    for query in queries {
+        packages.append(
+            &mut query
+                .color(PacmanColor::Never)
+                .query_with_output()
+                .await
+                .silent_unwrap(AppExitCode::PacmanError),
+        );
+    }
+    print_json("query", json!({ "packages": packages }));
+}
+
 #[tracing::instrument(level = "trace")]
 fn cmd_gencomp(args: &GenCompArgs) {
     if args.shell == "fig" {
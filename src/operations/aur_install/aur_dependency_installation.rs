@@ -1,75 +1,155 @@
-use aur_rpc::PackageInfo;
+use std::path::PathBuf;
+
 use futures::future;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     builder::{makepkg::MakePkgBuilder, pacman::PacmanInstallBuilder},
-    internal::{dependencies::DependencyInformation, error::AppResult},
+    internal::{chroot::Chroot, dependencies::DependencyInformation, error::AppResult},
     multi_progress, normal_output, numeric,
     operations::{
-        aur_install::common::{build_and_install, create_dependency_batches, download_aur_source},
+        aur_install::common::{build_and_install, collect_archives, download_aur_source},
         BuildContext,
     },
 };
 
-use super::aur_package_install::AurPackageInstall;
+use super::{
+    aur_package_install::AurPackageInstall,
+    build_report::BuildReport,
+    journal::{self, Stage},
+    BuildStep,
+};
 
+#[derive(Deserialize, Serialize)]
 pub struct AurDependencyInstallation {
     pub options: crate::internal::structs::Options,
     pub dependencies: Vec<DependencyInformation>,
+    /// The AUR dependencies that still need to be installed in the order of installation
+    pub batches: Vec<Vec<BuildContext>>,
     pub contexts: Vec<BuildContext>,
+    pub report: BuildReport,
+    /// Built AUR dependencies that are installed into the chroot for chroot builds
+    pub chroot_packages: Vec<PathBuf>,
+    /// Batches of AUR dependencies that failed or were skipped with `--keep-going`.
+    /// They are retried first when the installation is resumed.
+    #[serde(default)]
+    pub failed: Vec<Vec<BuildContext>>,
 }
 
 impl AurDependencyInstallation {
     #[tracing::instrument(level = "trace", skip_all)]
-    pub async fn install_aur_dependencies(self) -> AppResult<AurPackageInstall> {
-        let aur_dependencies: Vec<&PackageInfo> = self
-            .dependencies
+    pub async fn install_aur_dependencies(mut self) -> AppResult<AurPackageInstall> {
+        let failed = std::mem::take(&mut self.failed);
+        self.batches.splice(0..0, failed);
+        self.report.forget_failures();
+        journal::save(Stage::AurDependencyInstallation, &self)?;
+
+        let package_count = self
+            .batches
             .iter()
-            .flat_map(DependencyInformation::all_aur_depends)
-            .collect();
+            .flatten()
+            .map(|c| c.selected.len())
+            .sum::<usize>();
 
-        if !aur_dependencies.is_empty() {
+        if package_count > 0 {
             tracing::info!(
                 "Installing {} from the aur",
-                numeric!(aur_dependencies.len(), "package"["s"])
+                numeric!(package_count, "package"["s"])
             );
-            let batches = create_dependency_batches(aur_dependencies);
-            tracing::debug!("aur install batches: {batches:?}");
+        }
 
-            for batch in batches {
-                self.install(batch).await.unwrap();
-            }
+        while !self.batches.is_empty() {
+            let result = self.install_next_batch().await;
+            journal::save(Stage::AurDependencyInstallation, &self)?;
+            result?;
         }
 
         Ok(AurPackageInstall {
             options: self.options,
             dependencies: self.dependencies,
             contexts: self.contexts,
+            report: self.report,
+            chroot_packages: self.chroot_packages,
+            failed_dependencies: self.failed,
         })
     }
 
+    /// Downloads, builds and installs the first remaining batch of dependencies
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn install(&self, deps: Vec<&PackageInfo>) -> AppResult<()> {
-        multi_progress!();
+    async fn install_next_batch(&mut self) -> AppResult<()> {
+        let dep_contexts = &mut self.batches[0];
 
-        let dep_contexts = future::try_join_all(
-            deps.into_iter()
-                .map(BuildContext::from)
+        multi_progress!();
+        let downloaded = future::try_join_all(
+            dep_contexts
+                .iter_mut()
+                .filter(|ctx| matches!(ctx.step, BuildStep::Download))
                 .map(download_aur_source),
         )
-        .await?;
-
+        .await;
         normal_output!();
+        downloaded?;
 
         build_and_install(
             dep_contexts,
-            MakePkgBuilder::default().as_deps(true),
+            MakePkgBuilder::default()
+                .as_deps(true)
+                .chroot(
+                    self.options
+                        .chroot
+                        .then(|| Chroot::default().path().to_owned()),
+                )
+                .chroot_packages(&self.chroot_packages),
             PacmanInstallBuilder::default()
                 .no_confirm(self.options.noconfirm)
                 .as_deps(true),
+            &mut self.report,
+            self.options.keep_going,
         )
         .await?;
 
+        let batch = self.batches.remove(0);
+        let built_bases: Vec<&str> = batch
+            .iter()
+            .filter(|ctx| matches!(ctx.step, BuildStep::Done(_)))
+            .map(|ctx| ctx.package_base.as_str())
+            .collect();
+        self.reuse_built_bases(&built_bases).await?;
+
+        let mut failed = Vec::new();
+
+        for ctx in batch {
+            if let BuildStep::Done(archives) = ctx.step {
+                self.chroot_packages.extend(archives.0);
+            } else {
+                failed.push(ctx);
+            }
+        }
+        if !failed.is_empty() {
+            self.failed.push(failed);
+        }
+
+        Ok(())
+    }
+
+    /// Package bases can be required by several batches and by the targets when their
+    /// packages are split. The archives of all split packages are produced by the first
+    /// build so later contexts of the same base are installed from them without rebuilding.
+    async fn reuse_built_bases(&mut self, built_bases: &[&str]) -> AppResult<()> {
+        let pending = self
+            .batches
+            .iter_mut()
+            .flatten()
+            .chain(self.contexts.iter_mut())
+            .filter(|ctx| built_bases.contains(&ctx.package_base.as_str()))
+            .filter(|ctx| matches!(ctx.step, BuildStep::Download | BuildStep::Build(_)));
+
+        for ctx in pending {
+            tracing::debug!("Reusing the archives of {}", ctx.package_base);
+            let build_path = ctx.pkgbuild_directory();
+            collect_archives(ctx, &build_path).await?;
+        }
+
         Ok(())
     }
 }
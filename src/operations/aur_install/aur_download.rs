@@ -1,20 +1,29 @@
-use aur_rpc::PackageInfo;
+use std::collections::{HashMap, HashSet};
 
+use crossterm::style::Stylize;
 use futures::future;
+use serde_json::json;
 
 use crate::{
-    internal::{dependencies::DependencyInformation, error::AppResult, structs::Options},
+    internal::{
+        dependencies::DependencyInformation, error::AppResult, rpc::PackageInfo, structs::Options,
+    },
+    logging::{
+        get_logger,
+        output::{print_dependency_list, print_json},
+    },
     multi_progress, normal_output,
     operations::BuildContext,
 };
 
-use super::aur_review::AurReview;
+use super::{aur_review::AurReview, common::create_dependency_batches, PackageSource};
 
 pub struct AurDownload {
     pub options: Options,
     pub package_infos: Vec<PackageInfo>,
-    pub packages: Vec<String>,
     pub dependencies: Vec<DependencyInformation>,
+    /// The sources of all package bases that aren't downloaded from the AUR
+    pub sources: HashMap<String, PackageSource>,
 }
 
 impl AurDownload {
@@ -23,22 +32,125 @@ impl AurDownload {
         tracing::info!("Downloading sources");
         multi_progress!();
 
-        let contexts = future::try_join_all(
-            self.package_infos
-                .into_iter()
-                .map(BuildContext::from)
-                .map(super::common::download_aur_source),
-        )
-        .await?;
+        let mut contexts = BuildContext::group_by_base(self.package_infos.iter().cloned());
+        for ctx in &mut contexts {
+            if let Some(source) = self.sources.get(&ctx.package_base) {
+                ctx.source = source.clone();
+            }
+        }
+        future::try_join_all(contexts.iter_mut().map(super::common::download_aur_source)).await?;
 
         normal_output!();
         tracing::info!("All sources are ready.");
 
+        let packages: Vec<&PackageInfo> = contexts.iter().flat_map(|c| &c.packages).collect();
+        let changed: Vec<&str> = packages
+            .iter()
+            .filter(|p| {
+                !self
+                    .package_infos
+                    .iter()
+                    .any(|o| o.metadata.name == p.metadata.name && same_dependencies(o, p))
+            })
+            .map(|p| p.metadata.name.as_str())
+            .collect();
+
+        let dependencies = if changed.is_empty() {
+            self.dependencies
+        } else {
+            tracing::info!(
+                "The dependencies of {} differ from the AUR, resolving them again",
+                changed.join(", ")
+            );
+            let dependencies =
+                DependencyInformation::for_packages(packages.iter().copied(), self.options).await?;
+            print_dependency_list(&dependencies);
+
+            dependencies
+        };
+
         Ok(AurReview {
             options: self.options,
-            packages: self.packages,
-            dependencies: self.dependencies,
+            dependencies,
             contexts,
         })
     }
+
+    /// Prints the steps the installation would take without downloading or building anything
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn print_plan(&self) {
+        let repo_dependencies: HashSet<&str> = self
+            .dependencies
+            .iter()
+            .flat_map(DependencyInformation::all_repo_depends)
+            .collect();
+        let aur_dependencies: Vec<&PackageInfo> = self
+            .dependencies
+            .iter()
+            .flat_map(DependencyInformation::all_aur_depends)
+            .collect();
+        let mut build_order: Vec<Vec<String>> = create_dependency_batches(aur_dependencies)
+            .into_iter()
+            .map(|batch| {
+                BuildContext::group_by_base(batch.into_iter().cloned())
+                    .into_iter()
+                    .map(|ctx| ctx.package_base)
+                    .collect()
+            })
+            .collect();
+        build_order.push(
+            BuildContext::group_by_base(self.package_infos.iter().cloned())
+                .into_iter()
+                .map(|ctx| ctx.package_base)
+                .collect(),
+        );
+        let make_dependencies: HashSet<&str> = self
+            .dependencies
+            .iter()
+            .flat_map(DependencyInformation::make_depends)
+            .collect();
+
+        if self.options.json {
+            print_json(
+                "aur_install",
+                json!({
+                    "packages": self.package_infos.iter().map(|p| &p.metadata.name).collect::<Vec<_>>(),
+                    "repo_dependencies": repo_dependencies,
+                    "build_order": build_order,
+                    "make_dependencies": make_dependencies,
+                    "chroot": self.options.chroot,
+                }),
+            );
+            return;
+        }
+        tracing::info!("Dry run, nothing will be installed");
+
+        if !repo_dependencies.is_empty() && !self.options.chroot {
+            tracing::info!("Would install repo dependencies");
+            get_logger().print_list(&repo_dependencies, "  ", 2);
+            get_logger().print_newline();
+        }
+        tracing::info!("Would build in the following order");
+        get_logger().print_list(
+            build_order
+                .iter()
+                .filter(|batch| !batch.is_empty())
+                .enumerate()
+                .map(|(i, batch)| format!("{} {}", format!("{}.", i + 1).dim(), batch.join(" "))),
+            "\n",
+            2,
+        );
+        get_logger().print_newline();
+
+        if !make_dependencies.is_empty() {
+            tracing::info!("Make dependencies that can be removed afterwards");
+            get_logger().print_list(&make_dependencies, "  ", 2);
+            get_logger().print_newline();
+        }
+    }
+}
+
+/// Returns whether both packages have the same dependencies
+fn same_dependencies(a: &PackageInfo, b: &PackageInfo) -> bool {
+    a.depends == b.depends && a.make_depends == b.make_depends && a.check_depends == b.check_depends
 }
@@ -1,21 +1,30 @@
+use std::collections::HashMap;
+
 use crossterm::style::Stylize;
-use futures::future;
 
 use crate::{
     internal::{
+        conflicts,
         dependencies::DependencyInformation,
         error::{AppError, AppResult},
+        pkgbuild_repos,
+        rpc::PackageInfo,
         structs::Options,
     },
-    logging::output::{print_aur_package_list, print_dependency_list},
+    logging::output::{print_aur_package_list, print_conflict_list, print_dependency_list},
     normal_output, prompt, spinner,
 };
 
-use super::aur_download::AurDownload;
+use super::{
+    aur_download::AurDownload,
+    common::{read_local_sources, remove_conflicting_packages},
+    PackageSource,
+};
 
 pub struct AurFetch {
     pub options: Options,
     pub packages: Vec<String>,
+    pub sources: Vec<PackageSource>,
 }
 
 impl AurFetch {
@@ -23,7 +32,7 @@ impl AurFetch {
     pub async fn fetch_package_info(self) -> AppResult<AurDownload> {
         let pb = spinner!("Fetching package information");
 
-        let package_infos = aur_rpc::info(&self.packages).await?;
+        let mut package_infos = pkgbuild_repos::info_many(&self.packages).await?;
 
         tracing::debug!("package info = {package_infos:?}");
 
@@ -36,12 +45,24 @@ impl AurFetch {
             return Err(AppError::MissingDependencies(not_found));
         }
 
+        let mut sources = HashMap::new();
+
+        for (source, mut infos) in read_local_sources(&self.sources).await? {
+            sources.extend(
+                infos
+                    .iter()
+                    .map(|i| (i.metadata.package_base.clone(), source.clone())),
+            );
+            package_infos.append(&mut infos);
+        }
+
         pb.finish_with_message("All packages found".green().to_string());
         normal_output!();
 
-        if print_aur_package_list(&package_infos.iter().collect::<Vec<_>>()).await
+        if print_aur_package_list(&package_infos.iter().collect::<Vec<_>>())
             && !self.options.noconfirm
             && !self.options.upgrade
+            && !self.options.dry_run
             && !prompt!(default yes, "Some packages are already installed. Continue anyway?")
         {
             return Err(AppError::UserCancellation);
@@ -49,29 +70,38 @@ impl AurFetch {
 
         let pb = spinner!("Fetching package information");
 
-        let dependencies = future::try_join_all(
-            package_infos
-                .iter()
-                .map(|pkg| async { DependencyInformation::for_package(pkg).await }),
-        )
-        .await?;
+        let dependencies =
+            DependencyInformation::for_packages(&package_infos, self.options).await?;
 
         pb.finish_and_clear();
         normal_output!();
 
-        print_dependency_list(&dependencies).await;
+        print_dependency_list(&dependencies);
+
+        let aur_packages: Vec<&PackageInfo> = package_infos
+            .iter()
+            .chain(dependencies.iter().flat_map(|d| d.all_aur_depends()))
+            .collect();
+        let repo_packages: Vec<&str> = dependencies
+            .iter()
+            .flat_map(|d| d.all_repo_depends())
+            .collect();
+        let conflicts = conflicts::find_conflicts(&aur_packages, &repo_packages)?;
+        print_conflict_list(&conflicts);
+        conflicts::check_transaction_conflicts(&conflicts)?;
 
         if !self.options.noconfirm
+            && !self.options.dry_run
             && !prompt!(default yes, "Do you want to install these packages and package dependencies?")
         {
-            Err(AppError::UserCancellation)
-        } else {
-            Ok(AurDownload {
-                options: self.options,
-                packages: self.packages,
-                package_infos,
-                dependencies,
-            })
+            return Err(AppError::UserCancellation);
         }
+        remove_conflicting_packages(&conflicts, self.options).await?;
+        Ok(AurDownload {
+            options: self.options,
+            package_infos,
+            dependencies,
+            sources,
+        })
     }
 }
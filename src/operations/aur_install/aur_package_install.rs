@@ -1,37 +1,89 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
 use crate::{
     builder::{makepkg::MakePkgBuilder, pacman::PacmanInstallBuilder},
-    internal::{dependencies::DependencyInformation, error::AppResult, structs::Options},
+    internal::{
+        chroot::Chroot, dependencies::DependencyInformation, error::AppResult, structs::Options,
+    },
     numeric,
     operations::aur_install::{
+        aur_dependency_installation::AurDependencyInstallation, aur_review::mark_reviewed,
         common::build_and_install, make_dependency_removal::MakeDependencyRemoval,
     },
 };
 
-use super::BuildContext;
+use super::{
+    build_report::BuildReport,
+    journal::{self, Stage},
+    BuildContext, BuildStep,
+};
 
+#[derive(Deserialize, Serialize)]
 pub struct AurPackageInstall {
     pub options: Options,
     pub dependencies: Vec<DependencyInformation>,
     pub contexts: Vec<BuildContext>,
+    pub report: BuildReport,
+    pub chroot_packages: Vec<PathBuf>,
+    /// AUR dependencies that failed or were skipped with `--keep-going`
+    #[serde(default)]
+    pub failed_dependencies: Vec<Vec<BuildContext>>,
 }
 
 impl AurPackageInstall {
     #[tracing::instrument(level = "trace", skip_all)]
-    pub async fn install_packages(self) -> AppResult<MakeDependencyRemoval> {
-        tracing::info!(
-            "Installing {}",
-            numeric!(self.contexts.len(), "package"["s"])
-        );
-        build_and_install(
-            self.contexts,
-            MakePkgBuilder::default(),
+    pub async fn install_packages(mut self) -> AppResult<MakeDependencyRemoval> {
+        journal::save(Stage::AurPackageInstall, &self)?;
+
+        let package_count = self
+            .contexts
+            .iter()
+            .map(|c| c.selected.len())
+            .sum::<usize>();
+        tracing::info!("Installing {}", numeric!(package_count, "package"["s"]));
+        let result = build_and_install(
+            &mut self.contexts,
+            MakePkgBuilder::default()
+                .chroot(
+                    self.options
+                        .chroot
+                        .then(|| Chroot::default().path().to_owned()),
+                )
+                .chroot_packages(&self.chroot_packages),
             PacmanInstallBuilder::default().no_confirm(self.options.noconfirm),
+            &mut self.report,
+            self.options.keep_going,
         )
-        .await?;
+        .await;
+        journal::save(Stage::AurPackageInstall, &self)?;
+
+        for ctx in &self.contexts {
+            if matches!(ctx.step, BuildStep::Done(_)) {
+                mark_reviewed(ctx).await?;
+            }
+        }
+        result?;
+
+        if self.report.is_partial() {
+            // keeps everything that wasn't installed for `ame resume`
+            let retry = AurDependencyInstallation {
+                options: self.options,
+                dependencies: self.dependencies.clone(),
+                batches: Vec::new(),
+                contexts: std::mem::take(&mut self.contexts),
+                report: BuildReport::default(),
+                chroot_packages: self.chroot_packages.clone(),
+                failed: std::mem::take(&mut self.failed_dependencies),
+            };
+            journal::save(Stage::AurDependencyInstallation, &retry)?;
+        }
 
         Ok(MakeDependencyRemoval {
             options: self.options,
             dependencies: self.dependencies,
+            report: self.report,
         })
     }
 }
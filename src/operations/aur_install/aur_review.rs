@@ -1,37 +1,78 @@
-use tokio::fs;
+use std::fs;
 
 use crate::{
-    builder::pager::PagerBuilder,
+    builder::git::{GitDiffBuilder, GitRefBuilder, GitShowBuilder},
+    interact::page_string,
     internal::{
+        config::Config,
+        conflicts::{self, Conflict},
         dependencies::DependencyInformation,
         error::{AppError, AppResult},
+        lint::lint_package,
+        rpc::PackageInfo,
+        srcinfo::SrcInfo,
         structs::Options,
-        utils::get_cache_dir,
     },
-    multi_select, prompt, select_opt,
+    logging::output::{print_conflict_list, print_dependency_list, print_lint_findings},
+    multi_select, normal_output, prompt, spinner,
 };
 
-use super::{repo_dependency_installation::RepoDependencyInstallation, BuildContext};
+use super::{
+    common::remove_conflicting_packages, repo_dependency_installation::RepoDependencyInstallation,
+    BuildContext,
+};
+
+/// The git reference pointing to the last reviewed commit of a package
+const REVIEWED_REF: &str = "refs/amethyst/reviewed";
 
 pub struct AurReview {
     pub options: Options,
-    pub packages: Vec<String>,
     pub dependencies: Vec<DependencyInformation>,
     pub contexts: Vec<BuildContext>,
 }
 
 impl AurReview {
     #[tracing::instrument(level = "trace", skip_all)]
-    pub async fn review_pkgbuild(self) -> AppResult<RepoDependencyInstallation> {
+    pub async fn review_pkgbuild(mut self) -> AppResult<RepoDependencyInstallation> {
+        self.lint_packages().await?;
+
         if !self.options.noconfirm {
-            let to_review = multi_select!(&self.packages, "Select packages to review");
+            let mut changed = Vec::new();
+            // only unchanged and actually paged packages count as reviewed
+            let mut reviewed = Vec::new();
 
-            for pkg in to_review.into_iter().filter_map(|i| self.packages.get(i)) {
-                self.review_single_package(pkg).await?;
+            for ctx in &self.contexts {
+                let diff = pkgbuild_diff(ctx).await?;
+
+                if diff.trim().is_empty() {
+                    tracing::info!("{} is unchanged since the last review", ctx.package_base);
+                    reviewed.push(ctx.package_base.clone());
+                } else {
+                    changed.push((ctx.package_base.as_str(), diff));
+                }
+            }
+            if !changed.is_empty() {
+                let bases: Vec<&str> = changed.iter().map(|(base, _)| *base).collect();
+                let to_review = multi_select!(&bases, "Select packages to review");
+
+                for (pkg, diff) in to_review.into_iter().filter_map(|i| changed.get(i)) {
+                    tracing::info!("Reviewing {pkg}");
+                    page_string(diff)?;
+                    reviewed.push((*pkg).to_owned());
+                }
             }
             if !prompt!(default yes, "Do you still want to install those packages?") {
                 return Err(AppError::UserCancellation);
             }
+            let mut added = Vec::new();
+
+            for ctx in &mut self.contexts {
+                ctx.reviewed = reviewed.contains(&ctx.package_base);
+                added.append(&mut select_split_packages(ctx).await?);
+            }
+            if !added.is_empty() {
+                self.resolve_added_packages(&added).await?;
+            }
         }
         Ok(RepoDependencyInstallation {
             options: self.options,
@@ -40,37 +81,169 @@ impl AurReview {
         })
     }
 
-    async fn review_single_package(&self, pkg: &str) -> AppResult<()> {
-        tracing::info!("Reviewing {pkg}");
-        let mut files_iter = fs::read_dir(get_cache_dir().join(pkg)).await?;
-        let mut files = Vec::new();
+    /// Resolves the dependencies and conflicts of split packages that were added
+    /// during the review, the same way it's done for the requested packages
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn resolve_added_packages(&mut self, added: &[PackageInfo]) -> AppResult<()> {
+        let pb = spinner!("Fetching package information");
+        let mut dependencies = DependencyInformation::for_packages(added, self.options).await?;
+        pb.finish_and_clear();
+        normal_output!();
 
-        while let Some(file) = files_iter.next_entry().await? {
-            let path = file.path();
+        print_dependency_list(&dependencies);
 
-            if path.is_file() {
-                files.push(file.path());
-            }
-        }
+        let added_names: Vec<String> = added
+            .iter()
+            .map(|p| p.metadata.name.clone())
+            .chain(dependencies.iter().flat_map(|d| {
+                d.all_aur_depends()
+                    .into_iter()
+                    .map(|p| p.metadata.name.clone())
+                    .chain(d.all_repo_depends().into_iter().map(String::from))
+            }))
+            .collect();
+        self.dependencies.append(&mut dependencies);
 
-        let file_names = files
+        let aur_packages: Vec<&PackageInfo> = self
+            .contexts
             .iter()
-            .map(|f| f.file_name().unwrap())
-            .map(|f| f.to_string_lossy())
-            .collect::<Vec<_>>();
-
-        while let Some(selection) = select_opt!(&file_names, "Select a file to review") {
-            if let Some(path) = files.get(selection) {
-                if let Err(e) = PagerBuilder::default().path(path).open().await {
-                    tracing::debug!("Pager error {e}");
-                }
-            } else {
-                break;
+            .flat_map(|ctx| &ctx.packages)
+            .chain(self.dependencies.iter().flat_map(|d| d.all_aur_depends()))
+            .collect();
+        let repo_packages: Vec<&str> = self
+            .dependencies
+            .iter()
+            .flat_map(|d| d.all_repo_depends())
+            .collect();
+        // conflicts of the previously resolved packages were already handled
+        let conflicts: Vec<Conflict> = conflicts::find_conflicts(&aur_packages, &repo_packages)?
+            .into_iter()
+            .filter(|c| added_names.contains(&c.package) || added_names.contains(&c.conflicting))
+            .collect();
+        print_conflict_list(&conflicts);
+        conflicts::check_transaction_conflicts(&conflicts)?;
+
+        remove_conflicting_packages(&conflicts, self.options).await
+    }
+
+    /// Scans all packages for risky patterns. Installs without confirmation are aborted
+    /// if findings reach the configured severity threshold.
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn lint_packages(&self) -> AppResult<()> {
+        let threshold = Config::get().build.lint_threshold;
+        let mut blocked = Vec::new();
+
+        for ctx in &self.contexts {
+            let pkg_dir = ctx.build_path()?;
+            let previous_srcinfo = match reviewed_commit(ctx).await? {
+                Some(commit) => GitShowBuilder::default()
+                    .directory(pkg_dir)
+                    .revision(commit)
+                    .file(".SRCINFO")
+                    .show()
+                    .await
+                    .ok(),
+                None => None,
+            };
+            let findings = lint_package(pkg_dir, previous_srcinfo.as_deref())?;
+            print_lint_findings(&ctx.package_base, &findings);
+
+            if findings.iter().any(|f| f.severity >= threshold) {
+                blocked.push(ctx.package_base.clone());
             }
         }
 
-        tracing::info!("Done reviewing {pkg}");
+        if self.options.noconfirm && !blocked.is_empty() {
+            Err(AppError::LintBlocked(blocked))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Returns the last reviewed commit of the package's repository
+async fn reviewed_commit(ctx: &BuildContext) -> AppResult<Option<String>> {
+    // local directories might not be git repositories and PKGBUILD
+    // repositories share one history between all of their packages
+    if !ctx.source.has_review_history() {
+        return Ok(None);
+    }
+    let pkg_dir = ctx.build_path()?;
+
+    GitRefBuilder::default()
+        .directory(pkg_dir)
+        .reference(REVIEWED_REF)
+        .resolve()
+        .await
+}
+
+/// Returns the changes of the package's repository since the last reviewed commit
+async fn pkgbuild_diff(ctx: &BuildContext) -> AppResult<String> {
+    let pkg_dir = ctx.build_path()?;
 
-        Ok(())
+    if !ctx.source.has_review_history() {
+        return Ok(fs::read_to_string(pkg_dir.join("PKGBUILD"))?);
     }
+    let reviewed = reviewed_commit(ctx).await?;
+
+    GitDiffBuilder::default()
+        .directory(pkg_dir)
+        .from(reviewed)
+        .exclude(".SRCINFO")
+        .diff()
+        .await
+}
+
+/// Marks the current commit of the package's repository as reviewed.
+/// Called once the package was installed so that changes of failed or
+/// cancelled builds are shown again by the next review.
+pub async fn mark_reviewed(ctx: &BuildContext) -> AppResult<()> {
+    if !ctx.reviewed || !ctx.source.has_review_history() {
+        return Ok(());
+    }
+    GitRefBuilder::default()
+        .directory(ctx.pkgbuild_directory())
+        .reference(REVIEWED_REF)
+        .update_to_head()
+        .await
+}
+
+/// Lets the user choose additional packages to install from a split package base.
+/// Returns the information of the added packages.
+#[tracing::instrument(level = "trace", skip_all)]
+async fn select_split_packages(ctx: &mut BuildContext) -> AppResult<Vec<PackageInfo>> {
+    let srcinfo = SrcInfo::read_or_generate(ctx.build_path()?).await?;
+    let additional: Vec<&str> = srcinfo
+        .package_names()
+        .into_iter()
+        .filter(|name| !ctx.selected.iter().any(|s| s == name))
+        .collect();
+
+    if additional.is_empty() {
+        return Ok(Vec::new());
+    }
+    let selection = multi_select!(
+        &additional,
+        "{} is a split package. Select additional packages to install",
+        ctx.package_base
+    );
+    let selected: Vec<&str> = selection
+        .into_iter()
+        .filter_map(|i| additional.get(i).copied())
+        .collect();
+    let pkgbuild_source = ctx.packages.first().and_then(|p| p.pkgbuild_source.clone());
+    let added: Vec<PackageInfo> = srcinfo
+        .package_infos()
+        .into_iter()
+        .filter(|p| selected.contains(&p.metadata.name.as_str()))
+        .map(|p| PackageInfo {
+            pkgbuild_source: pkgbuild_source.clone(),
+            ..p
+        })
+        .collect();
+    ctx.selected
+        .extend(added.iter().map(|p| p.metadata.name.clone()));
+    ctx.packages.extend(added.iter().cloned());
+
+    Ok(added)
 }
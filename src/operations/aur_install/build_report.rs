@@ -0,0 +1,110 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use console::Alignment;
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+
+use crate::{internal::dependencies::Dependency, logging::get_logger};
+
+use super::BuildContext;
+
+/// Collects the outcome of every package of an installation
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct BuildReport {
+    entries: Vec<ReportEntry>,
+    /// Names and provisions of all packages that failed or were skipped
+    unavailable: HashSet<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ReportEntry {
+    package: String,
+    status: BuildStatus,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum BuildStatus {
+    Built,
+    Installed,
+    Skipped { failed_dependency: String },
+    Failed { log: PathBuf },
+}
+
+impl BuildReport {
+    /// Sets the status of all selected packages of the given context
+    pub fn record(&mut self, ctx: &BuildContext, status: BuildStatus) {
+        if matches!(
+            status,
+            BuildStatus::Failed { .. } | BuildStatus::Skipped { .. }
+        ) {
+            self.unavailable.extend(ctx.selected.iter().cloned());
+            self.unavailable.extend(
+                ctx.packages
+                    .iter()
+                    .flat_map(|p| &p.provides)
+                    .filter_map(|p| Dependency::try_from_str(p))
+                    .map(|p| p.name),
+            );
+        }
+        for package in &ctx.selected {
+            if let Some(entry) = self.entries.iter_mut().find(|e| &e.package == package) {
+                entry.status = status.clone();
+            } else {
+                self.entries.push(ReportEntry {
+                    package: package.clone(),
+                    status: status.clone(),
+                });
+            }
+        }
+    }
+
+    /// Returns the first dependency of the given context that failed or was skipped
+    pub fn failed_dependency(&self, ctx: &BuildContext) -> Option<String> {
+        ctx.packages
+            .iter()
+            .flat_map(|p| p.depends.iter().chain(p.make_depends.iter()))
+            .filter_map(|d| Dependency::try_from_str(d))
+            .map(|d| d.name)
+            .find(|name| self.unavailable.contains(name))
+    }
+
+    /// Forgets all failed and skipped packages so that they can be retried
+    pub fn forget_failures(&mut self) {
+        self.unavailable.clear();
+        self.entries
+            .retain(|e| matches!(e.status, BuildStatus::Built | BuildStatus::Installed));
+    }
+
+    /// Returns whether some packages couldn't be installed
+    pub fn is_partial(&self) -> bool {
+        !self.unavailable.is_empty()
+    }
+
+    pub fn print(&self) {
+        if self.entries.is_empty() {
+            return;
+        }
+        tracing::info!("Summary");
+        get_logger().print_list(
+            self.entries.iter().map(|entry| {
+                let status = match &entry.status {
+                    BuildStatus::Built => "built".yellow().to_string(),
+                    BuildStatus::Installed => "installed".green().to_string(),
+                    BuildStatus::Skipped { failed_dependency } => {
+                        format!("{} ({failed_dependency} failed)", "skipped".magenta())
+                    }
+                    BuildStatus::Failed { log } => {
+                        format!("{} (see {})", "failed".red(), log.display())
+                    }
+                };
+                format!(
+                    "{} {status}",
+                    console::pad_str(&entry.package, 30, Alignment::Left, Some("...")).bold()
+                )
+            }),
+            "\n",
+            2,
+        );
+        get_logger().print_newline();
+    }
+}
@@ -1,10 +1,9 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap, HashSet},
     path::{Path, PathBuf},
     sync::Arc,
 };
 
-use aur_rpc::PackageInfo;
 use crossterm::style::Stylize;
 use futures::future;
 use indicatif::ProgressBar;
@@ -19,14 +18,21 @@ use crate::{
     builder::{
         git::{GitCloneBuilder, GitPullBuilder},
         makepkg::MakePkgBuilder,
-        pacman::PacmanInstallBuilder,
+        pacman::{PacmanInstallBuilder, PacmanUninstallBuilder},
         pager::PagerBuilder,
     },
     crash,
     internal::{
         alpm::{Alpm, PackageFrom},
+        archives,
+        conflicts::{Conflict, ConflictKind},
+        devel,
         error::{AppError, AppResult},
         exit_code::AppExitCode,
+        local_repo::LocalRepo,
+        rpc::{aur_git_url, PackageInfo},
+        srcinfo::SrcInfo,
+        structs::Options,
         utils::{get_cache_dir, wrap_text},
     },
     logging::piped_stdio::StdioReader,
@@ -35,29 +41,68 @@ use crate::{
     prompt, spinner,
 };
 
-use super::{BuildContext, BuildPath, BuildStep};
+use super::{
+    build_report::{BuildReport, BuildStatus},
+    BuildContext, BuildPath, BuildStep, PackageSource,
+};
+
+/// Clones all git sources and reads the packages of all sources from their .SRCINFO
+#[tracing::instrument(level = "trace")]
+pub async fn read_local_sources(
+    sources: &[PackageSource],
+) -> AppResult<Vec<(PackageSource, Vec<PackageInfo>)>> {
+    let mut packages = Vec::new();
+
+    for (source, pkg_dir) in sources
+        .iter()
+        .filter_map(|s| s.local_directory().map(|dir| (s, dir)))
+    {
+        if let PackageSource::Git(url) = source {
+            if pkg_dir.exists() {
+                GitPullBuilder::default().directory(&pkg_dir).pull().await?;
+            } else {
+                GitCloneBuilder::default()
+                    .url(url)
+                    .directory(&pkg_dir)
+                    .clone()
+                    .await?;
+            }
+        }
+        if !pkg_dir.join("PKGBUILD").exists() {
+            return Err(AppError::from(format!(
+                "No PKGBUILD found in {}",
+                pkg_dir.display()
+            )));
+        }
+        let srcinfo = SrcInfo::read_or_generate(&pkg_dir).await?;
+        packages.push((source.clone(), srcinfo.package_infos()));
+    }
+
+    Ok(packages)
+}
 
 #[tracing::instrument(level = "trace", skip_all)]
-pub async fn download_aur_source(mut ctx: BuildContext) -> AppResult<BuildContext> {
-    let pkg_name = &ctx.package.metadata.name;
-    let base_pkg = &ctx.package.metadata.package_base;
-    let pb = spinner!("{}: Downloading sources", pkg_name.clone().bold());
+pub async fn download_aur_source(ctx: &mut BuildContext) -> AppResult<()> {
+    if let Some(pkg_dir) = ctx.source.local_directory() {
+        // local sources were already read when fetching the package information
+        return set_build_path(ctx, pkg_dir).await;
+    }
+    let package_base = &ctx.package_base;
+    let pb = spinner!("{}: Downloading sources", package_base.clone().bold());
 
-    let cache_dir = get_cache_dir();
-    let pkg_dir = cache_dir.join(&pkg_name);
+    let pkg_dir = ctx.pkgbuild_directory();
 
     if pkg_dir.exists() {
         pb.set_message(format!(
             "{}: Pulling latest changes",
-            pkg_name.clone().bold()
+            package_base.clone().bold()
         ));
         GitPullBuilder::default().directory(&pkg_dir).pull().await?;
     } else {
-        let aur_url = crate::internal::rpc::URL;
-        let repository_url = format!("{aur_url}/{base_pkg}");
+        let repository_url = aur_git_url(package_base);
         pb.set_message(format!(
             "{}: Cloning aur repository",
-            pkg_name.clone().bold()
+            package_base.clone().bold()
         ));
 
         GitCloneBuilder::default()
@@ -68,7 +113,7 @@ pub async fn download_aur_source(mut ctx: BuildContext) -> AppResult<BuildContex
 
         pb.set_message(format!(
             "{}: Downloading and extracting files",
-            pkg_name.clone().bold()
+            package_base.clone().bold()
         ));
 
         MakePkgBuilder::default()
@@ -82,12 +127,67 @@ pub async fn download_aur_source(mut ctx: BuildContext) -> AppResult<BuildContex
     }
     pb.finish_with_message(format!(
         "{}: {}",
-        pkg_name.clone().bold(),
+        package_base.clone().bold(),
         "Downloaded!".green()
     ));
+
+    set_build_path(ctx, pkg_dir).await
+}
+
+/// Updates the package information from the .SRCINFO and marks the context as ready to build
+async fn set_build_path(ctx: &mut BuildContext, pkg_dir: PathBuf) -> AppResult<()> {
+    // the clone might differ from the AUR metadata, e.g. when it has local changes
+    let srcinfo = SrcInfo::read_or_generate(&pkg_dir).await?;
+    ctx.packages
+        .iter_mut()
+        .for_each(|package| srcinfo.apply_to(package));
     ctx.step = BuildStep::Build(BuildPath(pkg_dir));
 
-    Ok(ctx)
+    Ok(())
+}
+
+/// Asks whether installed packages that conflict with or are replaced by the new packages
+/// should be removed before building. Declined removals are left to pacman, which asks
+/// again when installing the built archives. Without confirmation pacman would refuse
+/// so the installation is aborted before building.
+#[tracing::instrument(level = "trace", skip(conflicts))]
+pub async fn remove_conflicting_packages(
+    conflicts: &[Conflict],
+    options: Options,
+) -> AppResult<()> {
+    let installed_conflicts: Vec<&Conflict> = conflicts
+        .iter()
+        .filter(|c| c.kind != ConflictKind::Transaction)
+        .collect();
+
+    if installed_conflicts.is_empty() || options.dry_run {
+        return Ok(());
+    }
+    if options.noconfirm {
+        return Err(AppError::ConflictingPackages(
+            installed_conflicts
+                .iter()
+                .map(|c| format!("{} conflicts with installed {}", c.package, c.conflicting))
+                .collect(),
+        ));
+    }
+    let conflicting: Vec<&str> = installed_conflicts
+        .iter()
+        .map(|c| c.conflicting.as_str())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    if !prompt!(default yes, "Remove the conflicting packages {} now?", conflicting.join(", ")) {
+        tracing::info!("Pacman will ask to remove the conflicting packages during installation");
+        return Ok(());
+    }
+
+    PacmanUninstallBuilder::default()
+        .packages(conflicting)
+        .uninstall()
+        .await
+        .map_err(|_| AppError::Other(String::from("Failed to remove conflicting packages")))
 }
 
 #[tracing::instrument(level = "trace")]
@@ -151,42 +251,91 @@ pub fn create_dependency_batches(deps: Vec<&PackageInfo>) -> Vec<Vec<&PackageInf
     batches
 }
 
-#[tracing::instrument(level = "trace")]
+/// Builds all contexts that haven't been built yet and installs all built contexts.
+/// The build step of each context is updated in place so that the progress can be
+/// persisted even if some builds fail. With `keep_going` contexts depending on failed
+/// packages are skipped and build failures don't abort the installation.
+#[tracing::instrument(level = "trace", skip(report))]
 pub async fn build_and_install(
-    ctxs: Vec<BuildContext>,
+    ctxs: &mut [BuildContext],
     make_opts: MakePkgBuilder,
     install_opts: PacmanInstallBuilder,
+    report: &mut BuildReport,
+    keep_going: bool,
 ) -> AppResult<()> {
+    let mut skipped = HashSet::new();
+
+    if keep_going {
+        for ctx in ctxs.iter() {
+            if let Some(failed_dependency) = report.failed_dependency(ctx) {
+                tracing::warn!(
+                    "Skipping {} because {failed_dependency} failed",
+                    ctx.package_base
+                );
+                report.record(ctx, BuildStatus::Skipped { failed_dependency });
+                skipped.insert(ctx.package_base.clone());
+            }
+        }
+    }
+
     tracing::info!("Building packages");
     multi_progress!();
     let results = future::join_all(
-        ctxs.into_iter()
+        ctxs.iter_mut()
+            .filter(|ctx| matches!(ctx.step, BuildStep::Build(_)))
+            .filter(|ctx| !skipped.contains(&ctx.package_base))
             .map(|ctx| build_package(ctx, make_opts.clone())),
     )
     .await;
     normal_output!();
-    let mut ctxs = Vec::new();
+    let mut failed = Vec::new();
+
     for result in results {
-        match result {
-            Ok(ctx) => ctxs.push(ctx),
-            Err(e) => handle_build_error(e).await?,
+        if let Err(e) = result {
+            if let AppError::BuildError { pkg_name } = &e {
+                failed.push(pkg_name.clone());
+            }
+            handle_build_error(e, keep_going).await?;
         }
     }
+    for ctx in ctxs.iter() {
+        if failed.contains(&ctx.package_base) {
+            let log = build_log_path(&ctx.package_base);
+            report.record(ctx, BuildStatus::Failed { log });
+        } else if matches!(ctx.step, BuildStep::Install(_)) {
+            report.record(ctx, BuildStatus::Built);
+        }
+    }
+
+    let built: Vec<String> = ctxs
+        .iter()
+        .filter(|ctx| matches!(ctx.step, BuildStep::Install(_)))
+        .map(|ctx| ctx.package_base.clone())
+        .collect();
+    tracing::info!("Built {}", numeric!(built.len(), "package"["s"]));
 
-    tracing::info!("Built {}", numeric!(ctxs.len(), "package"["s"]));
-    tracing::info!("Installing packages");
+    if !built.is_empty() {
+        publish_packages(ctxs).await;
+        tracing::info!("Installing packages");
+        install_packages(ctxs, install_opts).await?;
 
-    install_packages(ctxs, install_opts).await?;
+        for ctx in ctxs.iter().filter(|ctx| built.contains(&ctx.package_base)) {
+            report.record(ctx, BuildStatus::Installed);
+        }
+    }
 
-    Ok(())
+    if failed.is_empty() || keep_going {
+        Ok(())
+    } else {
+        Err(AppError::BuildError {
+            pkg_name: failed.join(", "),
+        })
+    }
 }
 
 #[tracing::instrument(level = "trace")]
-async fn build_package(
-    mut ctx: BuildContext,
-    make_opts: MakePkgBuilder,
-) -> AppResult<BuildContext> {
-    let pkg_name = &ctx.package.metadata.name;
+async fn build_package(ctx: &mut BuildContext, make_opts: MakePkgBuilder) -> AppResult<()> {
+    let pkg_name = &ctx.package_base;
     let build_path = ctx.build_path()?;
     let pb = spinner!("{}: Building Package", pkg_name.as_str().bold());
 
@@ -221,47 +370,110 @@ async fn build_package(
         });
     }
 
+    let build_path = build_path.to_owned();
+    collect_archives(ctx, &build_path).await?;
+    pb.finish_with_message(format!(
+        "{}: {}",
+        ctx.package_base.as_str().bold(),
+        "Built!".green()
+    ));
+
+    Ok(())
+}
+
+/// Finds the archives of the selected packages in the build directory
+/// and marks the context as ready to install
+#[tracing::instrument(level = "trace", skip(ctx))]
+pub async fn collect_archives(ctx: &mut BuildContext, build_path: &Path) -> AppResult<()> {
     let archives = MakePkgBuilder::package_list(build_path).await?;
     tracing::debug!("Archives: {archives:?}");
 
     let mut pkgs_produced: HashMap<String, PathBuf> = HashMap::new();
-    let alpm = Alpm::new()?;
+    {
+        let alpm = Alpm::shared()?;
 
-    for archive in archives {
-        let pkg = alpm.load(PackageFrom::File(archive.clone()))?;
-        let name = pkg.name().to_owned();
-        pkgs_produced.insert(name, archive);
+        for archive in archives {
+            let pkg = alpm.load(PackageFrom::File(archive.clone()))?;
+            let name = pkg.name().to_owned();
+            pkgs_produced.insert(name, archive);
+        }
     }
     tracing::debug!("Produced packages: {pkgs_produced:#?}");
 
-    let pkg_to_install = pkgs_produced.get(pkg_name).ok_or_else(|| {
-        AppError::Other(format!(
-            "Could not find package {} in produced packages",
-            pkg_name.clone()
-        ))
-    })?;
+    let pkgs_to_install = ctx
+        .selected
+        .iter()
+        .map(|name| {
+            pkgs_produced.get(name).cloned().ok_or_else(|| {
+                AppError::Other(format!(
+                    "Could not find package {name} in produced packages"
+                ))
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+    ctx.step = BuildStep::Install(PackageArchives(pkgs_to_install));
 
-    pb.finish_with_message(format!("{}: {}", pkg_name.clone().bold(), "Built!".green()));
-    ctx.step = BuildStep::Install(PackageArchives(vec![pkg_to_install.to_path_buf()]));
+    Ok(())
+}
 
-    Ok(ctx)
+/// Adds all built packages to the configured local repository
+#[tracing::instrument(level = "trace", skip_all)]
+async fn publish_packages(ctxs: &[BuildContext]) {
+    if let Some(repo) = LocalRepo::from_config() {
+        let archives: Vec<PathBuf> = ctxs
+            .iter()
+            .filter_map(|ctx| ctx.archives().ok())
+            .flatten()
+            .cloned()
+            .collect();
+        tracing::info!("Publishing packages to {}", repo.db_path().display());
+
+        if let Err(e) = repo.add(&archives).await {
+            tracing::warn!("Failed to publish packages to the local repository: {e}");
+        }
+    }
 }
 
 #[tracing::instrument(level = "trace")]
 async fn install_packages(
-    mut ctxs: Vec<BuildContext>,
+    ctxs: &mut [BuildContext],
     install_opts: PacmanInstallBuilder,
-) -> AppResult<Vec<BuildContext>> {
+) -> AppResult<()> {
     let mut packages = Vec::new();
+    let mut installed = Vec::new();
+
+    for ctx in ctxs.iter_mut() {
+        if let Ok(archives) = ctx.archives() {
+            packages.append(&mut archives.clone());
+            let pkg_dir = ctx.pkgbuild_directory();
+            installed.extend(
+                ctx.selected
+                    .iter()
+                    .map(|name| (name.clone(), pkg_dir.clone())),
+            );
+        }
+    }
 
-    for ctx in &mut ctxs {
-        packages.append(&mut ctx.packages()?.clone());
-        ctx.step = BuildStep::Done;
+    install_opts
+        .files(packages.clone())
+        .needed(false)
+        .install()
+        .await?;
+
+    for ctx in ctxs.iter_mut() {
+        if let BuildStep::Install(archives) = &mut ctx.step {
+            ctx.step = BuildStep::Done(PackageArchives(std::mem::take(&mut archives.0)));
+        }
     }
 
-    install_opts.files(packages).needed(false).install().await?;
+    if let Err(e) = archives::store_archives(&packages) {
+        tracing::warn!("Failed to keep built archives for rollbacks: {e}");
+    }
+    if let Err(e) = devel::record_packages(&installed).await {
+        tracing::warn!("Failed to record VCS revisions: {e}");
+    }
 
-    Ok(ctxs)
+    Ok(())
 }
 
 #[tracing::instrument(level = "trace")]
@@ -272,7 +484,7 @@ async fn show_and_log_stdio(
     package_name: String,
 ) -> AppResult<()> {
     let mut reader = StdioReader::new(stdout, stderr);
-    let out_file = get_cache_dir().join(format!("{package_name}-build.log"));
+    let out_file = build_log_path(&package_name);
     let mut out_writer = BufWriter::new(
         OpenOptions::new()
             .create(true)
@@ -296,15 +508,17 @@ async fn show_and_log_stdio(
 }
 
 #[tracing::instrument(level = "trace", skip_all)]
-async fn handle_build_error<E: Into<AppError>>(err: E) -> AppResult<()> {
+async fn handle_build_error<E: Into<AppError>>(err: E, keep_going: bool) -> AppResult<()> {
     normal_output!();
     let err = err.into();
 
     match err {
         AppError::BuildError { pkg_name } => {
             tracing::error!("Failed to build package {pkg_name}!");
-            let log_path = get_cache_dir().join(format!("{pkg_name}-build.log"));
-            review_build_log(&log_path).await?;
+
+            if !keep_going {
+                review_build_log(&build_log_path(&pkg_name)).await?;
+            }
 
             Ok(())
         }
@@ -312,6 +526,11 @@ async fn handle_build_error<E: Into<AppError>>(err: E) -> AppResult<()> {
     }
 }
 
+/// Returns the path of the build log of the given package base
+fn build_log_path(pkg_name: &str) -> PathBuf {
+    get_cache_dir().join(format!("{pkg_name}-build.log"))
+}
+
 #[tracing::instrument(level = "trace")]
 async fn review_build_log(log_file: &Path) -> AppResult<()> {
     if prompt!(default yes, "Do you want to review the build log?") {
@@ -0,0 +1,105 @@
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::internal::{
+    error::{AppError, AppResult},
+    utils::get_cache_dir,
+};
+
+use super::{
+    aur_dependency_installation::AurDependencyInstallation, aur_package_install::AurPackageInstall,
+    make_dependency_removal::MakeDependencyRemoval,
+    repo_dependency_installation::RepoDependencyInstallation,
+};
+
+/// The stage of an AUR installation that was last entered
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    RepoDependencyInstallation,
+    AurDependencyInstallation,
+    AurPackageInstall,
+    MakeDependencyRemoval,
+}
+
+/// The persisted state of an unfinished AUR installation
+pub enum Journal {
+    RepoDependencyInstallation(RepoDependencyInstallation),
+    AurDependencyInstallation(AurDependencyInstallation),
+    AurPackageInstall(AurPackageInstall),
+    MakeDependencyRemoval(MakeDependencyRemoval),
+}
+
+#[derive(Serialize)]
+struct JournalEntry<'a, T: Serialize> {
+    stage: Stage,
+    state: &'a T,
+}
+
+#[derive(Deserialize)]
+struct StoredJournal {
+    stage: Stage,
+    state: serde_json::Value,
+}
+
+fn path() -> PathBuf {
+    get_cache_dir().join("transaction.json")
+}
+
+/// Returns whether there's an unfinished installation that can be resumed
+pub fn exists() -> bool {
+    path().exists()
+}
+
+/// Writes the state of the given stage to the journal, replacing the previous one
+#[tracing::instrument(level = "trace", skip(state))]
+pub fn save<T: Serialize>(stage: Stage, state: &T) -> AppResult<()> {
+    let content = serde_json::to_string_pretty(&JournalEntry { stage, state })
+        .map_err(|e| AppError::from(e.to_string()))?;
+    fs::write(path(), content)?;
+
+    Ok(())
+}
+
+/// Loads the journal of the last unfinished installation
+#[tracing::instrument(level = "trace")]
+pub fn load() -> AppResult<Option<Journal>> {
+    let path = path();
+
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)?;
+    let stored: StoredJournal =
+        serde_json::from_str(&content).map_err(|e| AppError::from(e.to_string()))?;
+    let state = stored.state;
+
+    let journal = match stored.stage {
+        Stage::RepoDependencyInstallation => {
+            serde_json::from_value(state).map(Journal::RepoDependencyInstallation)
+        }
+        Stage::AurDependencyInstallation => {
+            serde_json::from_value(state).map(Journal::AurDependencyInstallation)
+        }
+        Stage::AurPackageInstall => serde_json::from_value(state).map(Journal::AurPackageInstall),
+        Stage::MakeDependencyRemoval => {
+            serde_json::from_value(state).map(Journal::MakeDependencyRemoval)
+        }
+    }
+    .map_err(|e| AppError::from(e.to_string()))?;
+
+    Ok(Some(journal))
+}
+
+/// Removes the journal after an installation finished
+#[tracing::instrument(level = "trace")]
+pub fn clear() -> AppResult<()> {
+    let path = path();
+
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
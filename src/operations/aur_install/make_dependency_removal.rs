@@ -1,21 +1,54 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
     builder::pacman::PacmanUninstallBuilder,
-    internal::{dependencies::DependencyInformation, error::AppResult, structs::Options},
+    internal::{
+        dependencies::DependencyInformation,
+        error::{AppError, AppResult},
+        structs::Options,
+    },
     prompt,
 };
 
+use super::{
+    build_report::BuildReport,
+    journal::{self, Stage},
+};
+
+#[derive(Deserialize, Serialize)]
 pub struct MakeDependencyRemoval {
     pub options: Options,
     pub dependencies: Vec<DependencyInformation>,
+    pub report: BuildReport,
 }
 
 impl MakeDependencyRemoval {
     #[tracing::instrument(level = "trace", skip_all)]
     pub async fn remove_make_deps(self) -> AppResult<()> {
+        if self.report.is_partial() {
+            // the journal keeps the failed packages and make dependencies are
+            // still needed to retry them
+            self.report.print();
+            tracing::info!("Run `ame resume` to retry the packages that weren't installed");
+            return Err(AppError::PartialInstall);
+        }
+        journal::save(Stage::MakeDependencyRemoval, &self)?;
+
         let make_depends = self
             .dependencies
             .iter()
-            .flat_map(DependencyInformation::make_depends)
+            .flat_map(|d| {
+                // repo make dependencies of chroot builds were never installed on the host
+                let aur_make_depends: Vec<&str> = d
+                    .make_depends
+                    .aur
+                    .iter()
+                    .map(|p| p.metadata.name.as_str())
+                    .collect();
+                d.make_depends()
+                    .into_iter()
+                    .filter(move |m| !self.options.chroot || aur_make_depends.contains(m))
+            })
             .collect::<Vec<_>>();
         if !make_depends.is_empty()
             && !self.options.noconfirm
@@ -28,6 +61,11 @@ impl MakeDependencyRemoval {
                 .await?;
         }
 
+        journal::clear()?;
+
+        if self.options.keep_going {
+            self.report.print();
+        }
         tracing::info!("Done!");
 
         Ok(())
@@ -1,48 +1,113 @@
-use aur_rpc::PackageInfo;
-
 use std::path::{Path, PathBuf};
 
+use serde::{Deserialize, Serialize};
+
 use crate::internal::error::{AppError, AppResult};
+use crate::internal::pkgbuild_repos::PkgbuildSource;
+use crate::internal::rpc::PackageInfo;
+use crate::internal::utils::{get_cache_dir, stable_hash};
 
 use crate::internal::exit_code::AppExitCode;
-use crate::{cancelled, crash, Options};
+use crate::{cancelled, crash, prompt, Options};
 
 use self::aur_fetch::AurFetch;
+use self::journal::Journal;
 
 mod aur_dependency_installation;
 mod aur_download;
 mod aur_fetch;
 mod aur_package_install;
 mod aur_review;
+mod build_report;
 mod common;
+mod journal;
 mod make_dependency_removal;
 mod repo_dependency_installation;
 
-#[derive(Debug)]
+/// The build state of a single package base. Split packages
+/// requested from the same base share one context.
+#[derive(Debug, Deserialize, Serialize)]
 pub struct BuildContext {
-    pub package: PackageInfo,
+    pub package_base: String,
+    /// The requested packages of this package base
+    pub packages: Vec<PackageInfo>,
+    /// The names of all packages produced by the build that should be installed
+    pub selected: Vec<String>,
+    #[serde(default)]
+    pub source: PackageSource,
     pub step: BuildStep,
+    /// Whether the changes of the PKGBUILD were reviewed by the user
+    #[serde(default)]
+    pub reviewed: bool,
+}
+
+/// Where the PKGBUILD of a package base comes from
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub enum PackageSource {
+    #[default]
+    Aur,
+    /// A git repository containing a PKGBUILD in its root
+    Git(String),
+    /// A local directory containing a PKGBUILD
+    Directory(PathBuf),
+    /// A package directory inside a configured PKGBUILD repository
+    Repository(PkgbuildSource),
+}
+
+impl PackageSource {
+    /// Returns the directory of the PKGBUILD for sources that aren't cloned from the AUR
+    pub fn local_directory(&self) -> Option<PathBuf> {
+        match self {
+            Self::Aur => None,
+            Self::Git(url) => {
+                let name = url
+                    .trim_end_matches('/')
+                    .trim_end_matches(".git")
+                    .rsplit(['/', ':'])
+                    .next()
+                    .unwrap_or(url);
+                // repositories with the same name on different hosts must not share a clone
+                let directory = format!("{name}-{}", stable_hash(url));
+                Some(get_cache_dir().join("git").join(directory))
+            }
+            Self::Directory(path) => Some(path.clone()),
+            Self::Repository(source) => Some(source.directory.clone()),
+        }
+    }
+
+    /// Returns whether the PKGBUILD has its own git history to review changes against
+    pub fn has_review_history(&self) -> bool {
+        matches!(self, Self::Aur | Self::Git(_))
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Deserialize, Serialize)]
 pub enum BuildStep {
     Download,
     Build(BuildPath),
     Install(PackageArchives),
-    Done,
+    /// The package was installed from the contained archives
+    Done(PackageArchives),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct BuildPath(pub PathBuf);
 
-#[derive(Debug)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct PackageArchives(pub Vec<PathBuf>);
 
 impl From<PackageInfo> for BuildContext {
     fn from(package: PackageInfo) -> Self {
         Self {
-            package,
+            package_base: package.metadata.package_base.clone(),
+            selected: vec![package.metadata.name.clone()],
+            source: package
+                .pkgbuild_source
+                .clone()
+                .map_or(PackageSource::Aur, PackageSource::Repository),
+            packages: vec![package],
             step: BuildStep::Download,
+            reviewed: false,
         }
     }
 }
@@ -54,6 +119,35 @@ impl From<&PackageInfo> for BuildContext {
 }
 
 impl BuildContext {
+    /// Creates one context per package base for the given packages
+    pub fn group_by_base<I: IntoIterator<Item = PackageInfo>>(packages: I) -> Vec<Self> {
+        let mut contexts: Vec<Self> = Vec::new();
+
+        for package in packages {
+            let existing = contexts
+                .iter_mut()
+                .find(|c| c.package_base == package.metadata.package_base);
+
+            if let Some(ctx) = existing {
+                if !ctx.selected.contains(&package.metadata.name) {
+                    ctx.selected.push(package.metadata.name.clone());
+                }
+                ctx.packages.push(package);
+            } else {
+                contexts.push(Self::from(package));
+            }
+        }
+
+        contexts
+    }
+
+    /// Returns the directory containing the PKGBUILD of the package base
+    pub fn pkgbuild_directory(&self) -> PathBuf {
+        self.source
+            .local_directory()
+            .unwrap_or_else(|| get_cache_dir().join(&self.package_base))
+    }
+
     pub fn build_path(&self) -> AppResult<&Path> {
         if let BuildStep::Build(path) = &self.step {
             Ok(&path.0)
@@ -62,7 +156,7 @@ impl BuildContext {
         }
     }
 
-    pub fn packages(&self) -> AppResult<&Vec<PathBuf>> {
+    pub fn archives(&self) -> AppResult<&Vec<PathBuf>> {
         if let BuildStep::Install(pkgs) = &self.step {
             Ok(&pkgs.0)
         } else {
@@ -74,18 +168,32 @@ impl BuildContext {
 pub struct AurInstall {
     options: Options,
     packages: Vec<String>,
+    sources: Vec<PackageSource>,
 }
 
 impl AurInstall {
     pub fn new(options: Options, packages: Vec<String>) -> Self {
-        Self { options, packages }
+        Self {
+            options,
+            packages,
+            sources: Vec::new(),
+        }
+    }
+
+    /// Adds PKGBUILDs from git repositories or local directories to the installation
+    pub fn sources(mut self, sources: Vec<PackageSource>) -> Self {
+        self.sources = sources;
+
+        self
     }
 
     pub fn start(self) -> AurFetch {
         tracing::debug!("Installing from AUR: {:?}", &self.packages);
+        tracing::debug!("Installing from local sources: {:?}", &self.sources);
         AurFetch {
             options: self.options,
             packages: self.packages,
+            sources: self.sources,
         }
     }
 }
@@ -93,40 +201,118 @@ impl AurInstall {
 /// Installs a given list of packages from the aur
 #[tracing::instrument(level = "trace")]
 pub async fn aur_install(packages: Vec<String>, options: Options) {
-    if let Err(e) = aur_install_internal(AurInstall::new(options, packages)).await {
-        match e {
-            AppError::Rpc(e) => {
-                crash!(AppExitCode::RpcError, "AUR RPC Call failed with {e}")
-            }
-            AppError::BuildStepViolation => {
-                crash!(AppExitCode::MakePkgError, "Failed to build")
-            }
-            AppError::BuildError { pkg_name } => {
-                crash!(AppExitCode::MakePkgError, "Failed to build {pkg_name}")
-            }
-            AppError::UserCancellation => {
-                cancelled!();
-            }
-            AppError::MissingDependencies(deps) => {
-                crash!(
-                    AppExitCode::MissingDeps,
-                    "Missing dependencies {}",
-                    deps.join(", ")
-                )
-            }
-            AppError::MakePkg(msg) => {
-                crash!(AppExitCode::MakePkgError, "makepkg failed {msg}")
+    aur_install_with_sources(packages, Vec::new(), options).await;
+}
+
+/// Installs packages from the aur together with packages built from git repositories
+/// or local directories
+#[tracing::instrument(level = "trace")]
+pub async fn aur_install_with_sources(
+    packages: Vec<String>,
+    sources: Vec<PackageSource>,
+    options: Options,
+) {
+    if journal::exists()
+        && !options.noconfirm
+        && !options.dry_run
+        && !prompt!(default no, "An unfinished installation was found and will be discarded. Continue anyway?")
+    {
+        tracing::info!("Run `ame resume` to continue the unfinished installation");
+        cancelled!();
+    }
+    let install = AurInstall::new(options, packages).sources(sources);
+
+    if let Err(e) = aur_install_internal(install).await {
+        handle_install_error(e);
+    }
+}
+
+/// Continues the last AUR installation that didn't finish
+#[tracing::instrument(level = "trace")]
+pub async fn aur_resume() {
+    match journal::load() {
+        Ok(Some(journal)) => {
+            if let Err(e) = aur_resume_internal(journal).await {
+                handle_install_error(e);
             }
-            _ => crash!(AppExitCode::Other, "Unknown error"),
         }
+        Ok(None) => tracing::info!("There's no unfinished installation to resume"),
+        Err(e) => crash!(
+            AppExitCode::Other,
+            "Failed to load the unfinished installation: {e}"
+        ),
+    }
+}
+
+fn handle_install_error(e: AppError) {
+    match e {
+        AppError::Rpc(e) => {
+            crash!(AppExitCode::RpcError, "AUR RPC Call failed with {e}")
+        }
+        AppError::BuildStepViolation => {
+            crash!(AppExitCode::MakePkgError, "Failed to build")
+        }
+        AppError::BuildError { pkg_name } => {
+            crash!(
+                AppExitCode::MakePkgError,
+                "Failed to build {pkg_name}. Run `ame resume` to retry the remaining steps"
+            )
+        }
+        AppError::UserCancellation => {
+            cancelled!();
+        }
+        AppError::MissingDependencies(deps) => {
+            crash!(
+                AppExitCode::MissingDeps,
+                "Missing dependencies {}",
+                deps.join(", ")
+            )
+        }
+        AppError::UnsatisfiableDependencies {
+            pkg_name,
+            constraints,
+        } => {
+            crash!(
+                AppExitCode::MissingDeps,
+                "Unsatisfiable dependencies for {pkg_name}:\n  {}",
+                constraints.join("\n  ")
+            )
+        }
+        AppError::ConflictingPackages(conflicts) => {
+            crash!(
+                AppExitCode::PacmanError,
+                "Unresolvable package conflicts:\n  {}",
+                conflicts.join("\n  ")
+            )
+        }
+        AppError::LintBlocked(pkgs) => {
+            crash!(
+                AppExitCode::UserCancellation,
+                "Refusing to install {} without confirmation because of risky PKGBUILD patterns",
+                pkgs.join(", ")
+            )
+        }
+        AppError::PartialInstall => {
+            crash!(
+                AppExitCode::PartialInstall,
+                "Some packages could not be installed"
+            )
+        }
+        AppError::MakePkg(msg) => {
+            crash!(AppExitCode::MakePkgError, "makepkg failed {msg}")
+        }
+        _ => crash!(AppExitCode::Other, "Unknown error"),
     }
 }
 
 async fn aur_install_internal(install: AurInstall) -> AppResult<()> {
-    install
-        .start()
-        .fetch_package_info()
-        .await?
+    let download = install.start().fetch_package_info().await?;
+
+    if download.options.dry_run {
+        download.print_plan();
+        return Ok(());
+    }
+    download
         .download_sources()
         .await?
         .review_pkgbuild()
@@ -140,3 +326,35 @@ async fn aur_install_internal(install: AurInstall) -> AppResult<()> {
         .remove_make_deps()
         .await
 }
+
+async fn aur_resume_internal(journal: Journal) -> AppResult<()> {
+    match journal {
+        Journal::RepoDependencyInstallation(stage) => {
+            tracing::info!("Resuming installation of repo dependencies");
+            stage
+                .install_repo_dependencies()
+                .await?
+                .install_aur_dependencies()
+                .await?
+                .install_packages()
+                .await?
+                .remove_make_deps()
+                .await
+        }
+        Journal::AurDependencyInstallation(stage) => {
+            tracing::info!("Resuming installation of AUR dependencies");
+            stage
+                .install_aur_dependencies()
+                .await?
+                .install_packages()
+                .await?
+                .remove_make_deps()
+                .await
+        }
+        Journal::AurPackageInstall(stage) => {
+            tracing::info!("Resuming installation of AUR packages");
+            stage.install_packages().await?.remove_make_deps().await
+        }
+        Journal::MakeDependencyRemoval(stage) => stage.remove_make_deps().await,
+    }
+}
@@ -1,12 +1,27 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
     builder::pacman::PacmanInstallBuilder,
-    internal::{dependencies::DependencyInformation, error::AppResult, structs::Options},
+    internal::{
+        chroot::Chroot,
+        dependencies::{Dependency, DependencyInformation},
+        error::AppResult,
+        rpc::PackageInfo,
+        structs::Options,
+    },
 };
 
-use super::{aur_dependency_installation::AurDependencyInstallation, BuildContext};
+use super::{
+    aur_dependency_installation::AurDependencyInstallation,
+    build_report::BuildReport,
+    common::create_dependency_batches,
+    journal::{self, Stage},
+    BuildContext,
+};
 
+#[derive(Deserialize, Serialize)]
 pub struct RepoDependencyInstallation {
     pub options: Options,
     pub dependencies: Vec<DependencyInformation>,
@@ -16,13 +31,19 @@ pub struct RepoDependencyInstallation {
 impl RepoDependencyInstallation {
     #[tracing::instrument(level = "trace", skip_all)]
     pub async fn install_repo_dependencies(self) -> AppResult<AurDependencyInstallation> {
+        journal::save(Stage::RepoDependencyInstallation, &self)?;
+
         let repo_dependencies: HashSet<&str> = self
             .dependencies
             .iter()
             .flat_map(DependencyInformation::all_repo_depends)
             .collect();
 
-        if !repo_dependencies.is_empty() {
+        if self.options.chroot {
+            // repo dependencies are installed into the chroot by makechrootpkg
+            // and pulled in by pacman when installing the built packages
+            Chroot::default().prepare().await?;
+        } else if !repo_dependencies.is_empty() {
             tracing::info!("Installing repo dependencies");
             PacmanInstallBuilder::default()
                 .as_deps(true)
@@ -31,10 +52,61 @@ impl RepoDependencyInstallation {
                 .install()
                 .await?;
         }
+        let aur_dependencies: Vec<&PackageInfo> = self
+            .dependencies
+            .iter()
+            .flat_map(DependencyInformation::all_aur_depends)
+            .collect();
+        let batches = create_dependency_batches(aur_dependencies)
+            .into_iter()
+            .map(|batch| BuildContext::group_by_base(batch.into_iter().cloned()))
+            .collect();
+        tracing::debug!("aur install batches: {batches:?}");
+
+        let chroot_packages = if self.options.chroot {
+            self.host_packages()?
+        } else {
+            Vec::new()
+        };
+
         Ok(AurDependencyInstallation {
             options: self.options,
             dependencies: self.dependencies,
+            batches,
             contexts: self.contexts,
+            report: BuildReport::default(),
+            chroot_packages,
+            failed: Vec::new(),
         })
     }
+
+    /// Finds the archives of AUR dependencies that are already installed on the
+    /// host and therefore weren't resolved as dependencies to build
+    fn host_packages(&self) -> AppResult<Vec<PathBuf>> {
+        let packages: Vec<&PackageInfo> = self
+            .contexts
+            .iter()
+            .flat_map(|ctx| &ctx.packages)
+            .chain(
+                self.dependencies
+                    .iter()
+                    .flat_map(DependencyInformation::all_aur_depends),
+            )
+            .collect();
+        let built: HashSet<&str> = packages.iter().map(|p| p.metadata.name.as_str()).collect();
+        let dependencies = packages
+            .iter()
+            .flat_map(|p| {
+                p.depends
+                    .iter()
+                    .chain(&p.make_depends)
+                    .chain(&p.check_depends)
+            })
+            .map(String::as_str)
+            .filter(|d| {
+                Dependency::try_from_str(d).is_none_or(|d| !built.contains(d.name.as_str()))
+            });
+
+        Chroot::host_archives(dependencies)
+    }
 }
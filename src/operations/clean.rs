@@ -5,8 +5,11 @@ use crate::internal::error::SilentUnwrap;
 use crate::internal::exit_code::AppExitCode;
 
 use crate::internal::utils::get_cache_dir;
+use crate::logging::get_logger;
+use crate::logging::output::print_json;
 use crate::prompt;
 use crate::Options;
+use serde_json::json;
 
 /// Removes orphaned packages and cache
 #[tracing::instrument(level = "trace")]
@@ -20,6 +23,11 @@ pub async fn clean(options: Options) {
         .await
         .silent_unwrap(AppExitCode::PacmanError);
 
+    if options.dry_run {
+        print_clean_plan(&orphaned_packages.stdout, options.json);
+        return;
+    }
+
     if orphaned_packages.stdout.as_str().is_empty() {
         // If no orphaned packages found, do nothing
         tracing::info!("No orphaned packages found");
@@ -141,3 +149,31 @@ pub async fn clean(options: Options) {
         }
     }
 }
+
+/// Prints the orphans and caches a clean would remove
+fn print_clean_plan(orphaned_packages: &str, json: bool) {
+    let orphans: Vec<&str> = orphaned_packages
+        .lines()
+        .filter(|l| !l.is_empty())
+        .collect();
+
+    if json {
+        print_json(
+            "clean",
+            json!({ "orphans": orphans, "cache_dir": get_cache_dir() }),
+        );
+        return;
+    }
+
+    if orphans.is_empty() {
+        tracing::info!("No orphaned packages found");
+    } else {
+        tracing::info!("Would remove orphaned packages");
+        get_logger().print_list(&orphans, "  ", 2);
+        get_logger().print_newline();
+    }
+    tracing::info!(
+        "Would offer to clear Amethyst's PKGBUILD cache at {} and pacman's package cache",
+        get_cache_dir().display()
+    );
+}
@@ -0,0 +1,267 @@
+use chrono::{Local, TimeZone};
+use console::Alignment;
+use crossterm::style::Stylize;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::args::InfoArgs;
+use crate::internal::alpm::Alpm;
+use crate::internal::error::{AppError, SilentUnwrap};
+use crate::internal::exit_code::AppExitCode;
+use crate::internal::rpc::{rpcinfo_many, PackageInfo};
+use crate::logging::get_logger;
+use crate::logging::output::print_json;
+use crate::operations::VersionState;
+use crate::{crash, Options};
+
+/// The combined information about a repo or AUR package
+#[derive(Debug, Default, Serialize)]
+pub struct PackageDetails {
+    pub name: String,
+    /// The name of the sync db or `aur`
+    pub repo: String,
+    pub version: String,
+    pub installed_version: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub licenses: Vec<String>,
+    pub groups: Vec<String>,
+    pub keywords: Vec<String>,
+    pub maintainer: Option<String>,
+    pub votes: Option<u32>,
+    pub popularity: Option<f64>,
+    pub first_submitted: Option<u64>,
+    pub last_modified: Option<u64>,
+    pub out_of_date: Option<u64>,
+    /// The packager of repo packages
+    pub packager: Option<String>,
+    /// The build date of repo packages
+    pub build_date: Option<i64>,
+    pub depends: Vec<String>,
+    pub make_depends: Vec<String>,
+    pub check_depends: Vec<String>,
+    pub opt_depends: Vec<String>,
+    pub provides: Vec<String>,
+    pub conflicts: Vec<String>,
+    pub replaces: Vec<String>,
+}
+
+impl PackageDetails {
+    fn from_aur(package: PackageInfo, installed_version: Option<String>) -> Self {
+        Self {
+            name: package.metadata.name,
+            repo: String::from("aur"),
+            version: package.metadata.version,
+            installed_version,
+            description: package.metadata.description,
+            url: package.metadata.url,
+            licenses: package.license,
+            keywords: package.keywords,
+            maintainer: package.metadata.maintainer,
+            votes: Some(package.metadata.num_votes),
+            popularity: Some(package.metadata.popularity),
+            first_submitted: Some(package.metadata.first_submitted),
+            last_modified: Some(package.metadata.last_modified),
+            out_of_date: package.metadata.out_of_date,
+            depends: package.depends,
+            make_depends: package.make_depends,
+            check_depends: package.check_depends,
+            opt_depends: package.opt_depends,
+            provides: package.provides,
+            conflicts: package.conflicts,
+            replaces: package.replaces,
+            ..Default::default()
+        }
+    }
+
+    fn from_repo(package: &alpm::Package, installed_version: Option<String>) -> Self {
+        let to_strings =
+            |deps: alpm::AlpmList<alpm::Dep>| deps.iter().map(|d| d.to_string()).collect();
+
+        Self {
+            name: package.name().to_string(),
+            repo: package
+                .db()
+                .map(|db| db.name().to_string())
+                .unwrap_or_default(),
+            version: package.version().to_string(),
+            installed_version,
+            description: package.desc().map(String::from),
+            url: package.url().map(String::from),
+            licenses: package.licenses().iter().map(String::from).collect(),
+            groups: package.groups().iter().map(String::from).collect(),
+            packager: package.packager().map(String::from),
+            build_date: Some(package.build_date()),
+            depends: to_strings(package.depends()),
+            make_depends: to_strings(package.makedepends()),
+            check_depends: to_strings(package.checkdepends()),
+            opt_depends: to_strings(package.optdepends()),
+            provides: to_strings(package.provides()),
+            conflicts: to_strings(package.conflicts()),
+            replaces: to_strings(package.replaces()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Shows information about packages from the sync dbs and the AUR
+#[tracing::instrument(level = "trace")]
+pub async fn info(args: InfoArgs, options: Options) {
+    let both = !args.aur && !args.repo;
+    let mut details = Vec::new();
+    let mut remaining = Vec::new();
+
+    {
+        let alpm = Alpm::shared()
+            .map_err(AppError::from)
+            .silent_unwrap(AppExitCode::Other);
+        let handler = alpm.handler();
+
+        for name in args.packages {
+            let installed_version = handler
+                .localdb()
+                .pkg(name.as_str())
+                .ok()
+                .map(|p| p.version().to_string());
+            let repo_package = handler
+                .syncdbs()
+                .iter()
+                .find_map(|db| db.pkg(name.as_str()).ok());
+
+            match repo_package {
+                Some(package) if args.repo || both => {
+                    details.push(PackageDetails::from_repo(&package, installed_version));
+                }
+                _ => remaining.push((name, installed_version)),
+            }
+        }
+    }
+
+    let mut not_found = Vec::new();
+
+    if args.aur || both {
+        let mut aur_packages = rpcinfo_many(remaining.iter().map(|(name, _)| name))
+            .await
+            .silent_unwrap(AppExitCode::RpcError);
+
+        for (name, installed_version) in remaining {
+            match aur_packages.iter().position(|p| p.metadata.name == name) {
+                Some(index) => details.push(PackageDetails::from_aur(
+                    aur_packages.swap_remove(index),
+                    installed_version,
+                )),
+                None => not_found.push(name),
+            }
+        }
+    } else {
+        not_found.extend(remaining.into_iter().map(|(name, _)| name));
+    }
+
+    if options.json {
+        print_json(
+            "info",
+            json!({ "packages": details, "not_found": not_found }),
+        );
+    } else {
+        details.iter().for_each(print_details);
+    }
+    if !not_found.is_empty() {
+        crash!(
+            AppExitCode::NotFound,
+            "Couldn't find packages: {}",
+            not_found.join(", ")
+        );
+    }
+}
+
+fn print_details(details: &PackageDetails) {
+    let installed = details.installed_version.as_ref().map(|installed| {
+        let state = match VersionState::compare(installed, &details.version) {
+            VersionState::Equal => "up to date".green().to_string(),
+            VersionState::NewerUpstream => format!("{} available", details.version)
+                .yellow()
+                .to_string(),
+            VersionState::LocalNewer => "newer than available".magenta().to_string(),
+        };
+        format!("{installed} ({state})")
+    });
+    let mut fields = vec![
+        ("Repository", Some(details.repo.clone())),
+        ("Name", Some(details.name.clone().bold().to_string())),
+        ("Version", Some(details.version.clone())),
+        ("Installed", installed.or_else(|| Some(String::from("No")))),
+        ("Description", details.description.clone()),
+        ("URL", details.url.clone()),
+        ("Licenses", join_list(&details.licenses)),
+        ("Groups", join_list(&details.groups)),
+        ("Keywords", join_list(&details.keywords)),
+    ];
+    if details.repo == "aur" {
+        fields.append(&mut vec![
+            ("Maintainer", details.maintainer.clone()),
+            ("Votes", details.votes.map(|v| v.to_string())),
+            ("Popularity", details.popularity.map(|p| format!("{p:.2}"))),
+            (
+                "First Submitted",
+                details.first_submitted.and_then(format_unsigned_date),
+            ),
+            (
+                "Last Modified",
+                details.last_modified.and_then(format_unsigned_date),
+            ),
+            (
+                "Out Of Date",
+                details
+                    .out_of_date
+                    .and_then(format_unsigned_date)
+                    .map(|d| d.red().to_string()),
+            ),
+        ]);
+    } else {
+        fields.append(&mut vec![
+            ("Packager", details.packager.clone()),
+            ("Build Date", details.build_date.and_then(format_date)),
+        ]);
+    }
+    fields.append(&mut vec![
+        ("Depends On", join_list(&details.depends)),
+        ("Make Deps", join_list(&details.make_depends)),
+        ("Check Deps", join_list(&details.check_depends)),
+        ("Optional Deps", join_list(&details.opt_depends)),
+        ("Provides", join_list(&details.provides)),
+        ("Conflicts With", join_list(&details.conflicts)),
+        ("Replaces", join_list(&details.replaces)),
+    ]);
+
+    get_logger().print_list(
+        fields.into_iter().map(|(key, value)| {
+            format!(
+                "{} : {}",
+                console::pad_str(key, 16, Alignment::Left, None).bold(),
+                value.unwrap_or_else(|| String::from("None"))
+            )
+        }),
+        "\n",
+        0,
+    );
+    get_logger().print_newline();
+}
+
+fn join_list(list: &[String]) -> Option<String> {
+    if list.is_empty() {
+        None
+    } else {
+        Some(list.join("  "))
+    }
+}
+
+fn format_date(timestamp: i64) -> Option<String> {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+}
+
+fn format_unsigned_date(timestamp: u64) -> Option<String> {
+    timestamp.try_into().ok().and_then(format_date)
+}
@@ -1,9 +1,22 @@
 use crate::builder::pacman::PacmanInstallBuilder;
 use crate::internal::exit_code::AppExitCode;
+use crate::logging::get_logger;
+use crate::logging::output::print_json;
 use crate::{crash, Options};
+use serde_json::json;
 
 #[tracing::instrument(level = "trace")]
 pub async fn install(packages: Vec<String>, options: Options) {
+    if options.dry_run && options.json {
+        print_json("repo_install", json!({ "packages": packages }));
+        return;
+    }
+    if options.dry_run {
+        tracing::info!("Would install from repos");
+        get_logger().print_list(&packages, "  ", 2);
+        get_logger().print_newline();
+        return;
+    }
     tracing::info!("Installing packages {} from repos", &packages.join(", "));
 
     if !packages.is_empty() {
@@ -1,13 +1,17 @@
 pub use aur_install::*;
 pub use clean::*;
+pub use info::*;
 pub use install::*;
+pub use rollback::*;
 pub use search::{aur_search, repo_search as search, SearchBy};
 pub use uninstall::*;
 pub use upgrade::*;
 
 mod aur_install;
 mod clean;
+mod info;
 mod install;
+mod rollback;
 mod search;
 mod uninstall;
 mod upgrade;
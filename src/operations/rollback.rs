@@ -0,0 +1,104 @@
+use chrono::{Local, TimeZone};
+
+use crate::args::RollbackArgs;
+use crate::builder::pacman::PacmanInstallBuilder;
+use crate::internal::archives::{cached_archives, CachedArchive};
+use crate::internal::commands::ShellCommand;
+use crate::internal::error::SilentUnwrap;
+use crate::internal::exit_code::AppExitCode;
+use crate::internal::ignore::IgnoreList;
+use crate::{cancelled, crash, select_opt, Options};
+
+/// Reinstalls a previously built archive of an AUR package
+#[tracing::instrument(level = "trace")]
+pub async fn rollback(args: RollbackArgs, options: Options) {
+    let archives = cached_archives(&args.package).silent_unwrap(AppExitCode::Other);
+
+    if archives.is_empty() {
+        crash!(
+            AppExitCode::NotFound,
+            "No previous builds of {} were found",
+            args.package
+        );
+    }
+
+    let archive = if let Some(version) = &args.version {
+        archives
+            .into_iter()
+            .find(|a| &a.version == version)
+            .unwrap_or_else(|| {
+                crash!(
+                    AppExitCode::NotFound,
+                    "Version {version} of {} was not found in the archive cache",
+                    args.package
+                )
+            })
+    } else if options.noconfirm {
+        crash!(
+            AppExitCode::Other,
+            "A version needs to be specified when running with --noconfirm"
+        );
+    } else {
+        select_archive(&args.package, archives)
+    };
+
+    tracing::info!("Rolling back {} to {}", args.package, archive.version);
+    PacmanInstallBuilder::default()
+        .files([&archive.path])
+        .no_confirm(options.noconfirm)
+        .install()
+        .await
+        .silent_unwrap(AppExitCode::PacmanError);
+
+    if args.ignore {
+        ignore_package(&args.package).await;
+    }
+}
+
+fn select_archive(package: &str, mut archives: Vec<CachedArchive>) -> CachedArchive {
+    let items: Vec<String> = archives
+        .iter()
+        .map(|a| {
+            format!(
+                "{} (built {})",
+                a.version,
+                Local.timestamp(a.build_date, 0).format("%Y-%m-%d %H:%M")
+            )
+        })
+        .collect();
+
+    match select_opt!(&items, "Select the version of {package} to install") {
+        Some(index) if index < archives.len() => archives.swap_remove(index),
+        _ => cancelled!(),
+    }
+}
+
+/// Adds an IgnorePkg entry for the package to the options section of pacman.conf
+/// unless it's already ignored. If the entry can't be added the user is asked to add it.
+async fn ignore_package(package: &str) {
+    if is_ignored(package) {
+        tracing::info!("{package} is already ignored");
+        return;
+    }
+    tracing::info!("Adding {package} to IgnorePkg");
+    let added = ShellCommand::sed()
+        .elevated()
+        .arg("-i")
+        .arg(format!("/^\\[options\\]\\s*$/a IgnorePkg = {package}"))
+        .arg("/etc/pacman.conf")
+        .wait_success()
+        .await;
+
+    if let Err(e) = &added {
+        tracing::debug!("Failed to edit pacman.conf: {e}");
+    }
+    if added.is_err() || !is_ignored(package) {
+        tracing::warn!(
+            "Failed to add {package} to IgnorePkg. Add `IgnorePkg = {package}` to the [options] section of /etc/pacman.conf"
+        );
+    }
+}
+
+fn is_ignored(package: &str) -> bool {
+    IgnoreList::load(&[]).is_ok_and(|ignored| ignored.is_ignored(package))
+}
@@ -3,20 +3,20 @@ use std::fmt::Formatter;
 use std::str::FromStr;
 
 use crate::internal::alpm::Alpm;
-use crate::internal::error::SilentUnwrap;
+use crate::internal::error::{AppError, SilentUnwrap};
 use crate::internal::exit_code::AppExitCode;
-use crate::internal::rpc::rpcsearch;
+use crate::internal::pkgbuild_repos;
+use crate::internal::rpc::{rpcsearch, SearchField};
 use crate::internal::utils::wrap_text;
 use crate::logging::Printable;
 use crate::Options;
 
-use aur_rpc::SearchField;
 use chrono::Local;
 use chrono::TimeZone;
 use colored::Colorize;
 use trigram::similarity;
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 pub struct PackageSearchResult {
     pub repo: String,
     pub name: String,
@@ -131,18 +131,26 @@ pub async fn aur_search(
     by_field: Option<SearchBy>,
     options: Options,
 ) -> Vec<PackageSearchResult> {
-    let alpm = Alpm::new().unwrap();
-    let alpm = alpm.handler();
-
-    let local = alpm.localdb();
     let packages = rpcsearch(query.to_string(), by_field.map(SearchBy::into))
         .await
         .silent_unwrap(AppExitCode::RpcError);
+    // PKGBUILD repositories can only be searched by name and description
+    let repo_packages = if matches!(by_field, None | Some(SearchBy::Name | SearchBy::NameDesc)) {
+        pkgbuild_repos::search(query)
+            .await
+            .silent_unwrap(AppExitCode::Other)
+    } else {
+        Vec::new()
+    };
     let total_results = packages.len();
+    let alpm = Alpm::shared()
+        .map_err(AppError::from)
+        .silent_unwrap(AppExitCode::Other);
+    let local = alpm.handler().localdb();
 
     tracing::debug!("Found {total_results} resuls for \"{query}\" in AUR",);
 
-    let results: Vec<PackageSearchResult> = packages
+    let mut results: Vec<PackageSearchResult> = packages
         .into_iter()
         .map(|package| {
             let name = package.name;
@@ -164,12 +172,28 @@ pub async fn aur_search(
         })
         .collect();
 
+    results.extend(repo_packages.into_iter().filter_map(|package| {
+        let source = package.pkgbuild_source?;
+
+        Some(PackageSearchResult {
+            repo: source.repo,
+            installed: local.pkg(&*package.metadata.name).is_ok(),
+            name: package.metadata.name,
+            version: package.metadata.version,
+            groups: None,
+            out_of_date: None,
+            description: package.metadata.description,
+        })
+    }));
+
     results
 }
 
 #[tracing::instrument(level = "trace")]
 pub async fn repo_search(query: &str, options: Options) -> Vec<PackageSearchResult> {
-    let alpm = Alpm::new().unwrap();
+    let alpm = Alpm::shared()
+        .map_err(AppError::from)
+        .silent_unwrap(AppExitCode::Other);
     let alpm = alpm.handler();
 
     let local = alpm.localdb();
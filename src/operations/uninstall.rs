@@ -1,15 +1,23 @@
+use serde_json::json;
 use std::env;
 use std::path::Path;
 use tokio::fs;
 
+use crate::builder::pacman::BasicPackageInfo;
 use crate::internal::commands::ShellCommand;
 use crate::internal::error::SilentUnwrap;
 use crate::internal::exit_code::AppExitCode;
-use crate::Options;
+use crate::logging::get_logger;
+use crate::logging::output::print_json;
+use crate::{crash, Options};
 
 /// Uninstalls the given packages
 #[tracing::instrument(level = "trace")]
 pub async fn uninstall(packages: Vec<String>, options: Options) {
+    if options.dry_run {
+        print_removal_plan(&packages, options.json).await;
+        return;
+    }
     let mut pacman_args = vec!["-Rs"];
     pacman_args.append(&mut packages.iter().map(|s| s.as_str()).collect());
 
@@ -46,3 +54,35 @@ pub async fn uninstall(packages: Vec<String>, options: Options) {
         }
     }
 }
+
+/// Prints the packages pacman would remove including their unneeded dependencies
+async fn print_removal_plan(packages: &[String], json: bool) {
+    let output = ShellCommand::pacman()
+        .args(["-Rs", "--print", "--print-format", "%n %v"])
+        .args(packages)
+        .wait_with_output()
+        .await
+        .silent_unwrap(AppExitCode::PacmanError);
+
+    if !output.status.success() {
+        crash!(AppExitCode::PacmanError, "{}", output.stderr.trim());
+    }
+    let removals: Vec<&str> = output.stdout.lines().filter(|l| !l.is_empty()).collect();
+
+    if json {
+        let packages: Vec<BasicPackageInfo> = removals
+            .iter()
+            .filter_map(|l| l.split_once(' '))
+            .map(|(name, version)| BasicPackageInfo {
+                name: name.to_string(),
+                version: version.to_string(),
+            })
+            .collect();
+        print_json("removal", json!({ "packages": packages }));
+        return;
+    }
+
+    tracing::info!("Would remove");
+    get_logger().print_list(&removals, "\n", 2);
+    get_logger().print_newline();
+}
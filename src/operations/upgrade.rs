@@ -1,12 +1,73 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use chrono::{Local, TimeZone};
+use console::Alignment;
+use crossterm::style::Stylize;
+use serde::Serialize;
+use serde_json::json;
+
 use crate::args::UpgradeArgs;
 use crate::builder::pacman::{PacmanColor, PacmanQueryBuilder};
+use crate::internal::alpm::Alpm;
 use crate::internal::commands::ShellCommand;
 use crate::internal::detect;
+use crate::internal::devel;
 use crate::internal::error::SilentUnwrap;
 use crate::internal::exit_code::AppExitCode;
-use crate::internal::rpc::rpcinfo;
+use crate::internal::ignore::{IgnoreList, TemporaryIgnores};
+use crate::internal::pkgbuild_repos;
+use crate::internal::rpc::PackageInfo;
+use crate::logging::get_logger;
+use crate::logging::output::print_json;
 use crate::operations::aur_install::aur_install;
-use crate::{prompt, Options};
+use crate::{multi_select, numeric, prompt, Options};
+
+/// The result of comparing an installed AUR package with the version in the AUR
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersionState {
+    /// The AUR has a newer version than the installed one
+    NewerUpstream,
+    /// The installed version is newer than the one in the AUR
+    LocalNewer,
+    /// Both versions are equal
+    Equal,
+}
+
+impl VersionState {
+    /// Compares two full versions (`epoch:pkgver-pkgrel`) using pacman's version comparison
+    pub fn compare(local: &str, remote: &str) -> Self {
+        match alpm::vercmp(remote, local) {
+            Ordering::Greater => Self::NewerUpstream,
+            Ordering::Less => Self::LocalNewer,
+            Ordering::Equal => Self::Equal,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct AurVersionInfo {
+    name: String,
+    local_version: String,
+    remote_version: String,
+    last_modified: u64,
+    out_of_date: Option<u64>,
+    /// Whether the upgrade was detected through new upstream VCS commits
+    devel: bool,
+    /// The upstream revisions of the VCS sources of devel upgrades
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote_revision: Option<String>,
+}
+
+impl AurVersionInfo {
+    /// Returns what a deselected upgrade is skipped for. Devel upgrades keep the
+    /// same version until rebuilt so they're skipped until new commits are pushed.
+    fn ignored_version(&self) -> &str {
+        self.remote_revision
+            .as_deref()
+            .unwrap_or(&self.remote_version)
+    }
+}
 
 /// Upgrades all installed packages
 #[tracing::instrument(level = "trace")]
@@ -15,16 +76,20 @@ pub async fn upgrade(args: UpgradeArgs, options: Options) {
         upgrade_repo(options).await;
     }
     if args.aur {
-        upgrade_aur(options).await;
+        upgrade_aur(&args, options).await;
     }
     if !args.aur && !args.repo {
         upgrade_repo(options).await;
-        upgrade_aur(options).await;
+        upgrade_aur(&args, options).await;
     }
 }
 
 #[tracing::instrument(level = "trace")]
 async fn upgrade_repo(options: Options) {
+    if options.dry_run {
+        print_repo_upgrades(options.json).await;
+        return;
+    }
     let noconfirm = options.noconfirm;
 
     let mut pacman_args = vec!["-Syu"];
@@ -37,9 +102,11 @@ async fn upgrade_repo(options: Options) {
     let pacman_result = ShellCommand::pacman()
         .elevated()
         .args(pacman_args)
+        .stdout_to_stderr(options.json)
         .wait()
         .await
         .silent_unwrap(AppExitCode::PacmanError);
+    Alpm::invalidate_shared();
 
     if pacman_result.success() {
         tracing::info!("Successfully upgraded repo packages");
@@ -54,10 +121,48 @@ async fn upgrade_repo(options: Options) {
     }
 }
 
+/// Prints the repo packages with upgrades in the local sync databases
+async fn print_repo_upgrades(json: bool) {
+    let output = ShellCommand::pacman()
+        .arg("-Qu")
+        .wait_with_output()
+        .await
+        .silent_unwrap(AppExitCode::PacmanError);
+    let upgrades: Vec<&str> = output.stdout.lines().filter(|l| !l.is_empty()).collect();
+
+    if json {
+        let packages: Vec<_> = upgrades
+            .iter()
+            .filter_map(|l| {
+                let (name, versions) = l.split_once(' ')?;
+                let (local_version, remote_version) = versions.split_once(" -> ")?;
+                Some(json!({
+                    "name": name,
+                    "local_version": local_version,
+                    "remote_version": remote_version,
+                }))
+            })
+            .collect();
+        print_json("repo_upgrades", json!({ "packages": packages }));
+        return;
+    }
+    if upgrades.is_empty() {
+        tracing::info!("No upgrades available for repo packages");
+    } else {
+        tracing::info!("Would upgrade repo packages");
+        get_logger().print_list(&upgrades, "\n", 2);
+        get_logger().print_newline();
+    }
+}
+
 #[tracing::instrument(level = "trace")]
-async fn upgrade_aur(options: Options) {
+async fn upgrade_aur(args: &UpgradeArgs, options: Options) {
     tracing::debug!("Upgrading AUR packages");
 
+    if let Err(e) = pkgbuild_repos::sync().await {
+        tracing::warn!("Failed to update the PKGBUILD repositories: {e}");
+    }
+
     let non_native_pkgs = PacmanQueryBuilder::foreign()
         .color(PacmanColor::Never)
         .query_with_output()
@@ -65,37 +170,338 @@ async fn upgrade_aur(options: Options) {
         .silent_unwrap(AppExitCode::PacmanError);
 
     tracing::debug!("aur packages: {non_native_pkgs:?}");
-    let mut aur_upgrades = vec![];
+    let installed: Vec<String> = non_native_pkgs.iter().map(|p| p.name.clone()).collect();
+    let mut remote_packages: HashMap<String, PackageInfo> =
+        pkgbuild_repos::info_many(non_native_pkgs.iter().map(|p| &p.name))
+            .await
+            .silent_unwrap(AppExitCode::RpcError)
+            .into_iter()
+            .map(|p| (p.metadata.name.clone(), p))
+            .collect();
+    let ignore_list = IgnoreList::load(&args.ignore).unwrap_or_else(|e| {
+        tracing::warn!("Failed to load the ignored packages: {e}");
+        IgnoreList::default()
+    });
+    let mut newer_upstream = vec![];
+    let mut local_newer = vec![];
+    let mut ignored = vec![];
+    let mut unchanged = HashMap::new();
 
     for pkg in non_native_pkgs {
-        let remote_package = rpcinfo(&pkg.name)
-            .await
-            .silent_unwrap(AppExitCode::RpcError);
-
-        if let Some(remote_package) = remote_package {
-            if remote_package.metadata.version != pkg.version {
-                tracing::debug!(
-                    "local version: {}, remote version: {}",
-                    pkg.version,
-                    remote_package.metadata.version
-                );
-                aur_upgrades.push(pkg.name);
+        if let Some(remote_package) = remote_packages.remove(&pkg.name) {
+            let info = AurVersionInfo {
+                name: pkg.name,
+                local_version: pkg.version,
+                remote_version: remote_package.metadata.version,
+                last_modified: remote_package.metadata.last_modified,
+                out_of_date: remote_package.metadata.out_of_date,
+                devel: false,
+                remote_revision: None,
+            };
+            let state = VersionState::compare(&info.local_version, &info.remote_version);
+            tracing::debug!(
+                "{}: local version: {}, remote version: {}, state: {state:?}",
+                info.name,
+                info.local_version,
+                info.remote_version
+            );
+
+            match state {
+                VersionState::Equal => {
+                    unchanged.insert(info.name.clone(), info);
+                }
+                _ if ignore_list.is_ignored(&info.name) => ignored.push(info),
+                VersionState::NewerUpstream => newer_upstream.push(info),
+                VersionState::LocalNewer => local_newer.push(info),
             }
         } else {
             tracing::warn!("Could not find the remote package for {}", pkg.name);
         }
     }
 
-    if !aur_upgrades.is_empty() {
-        let options = Options {
-            upgrade: true,
-            ..options
-        };
-        aur_install(aur_upgrades, options).await;
+    if !ignored.is_empty() {
+        tracing::info!(
+            "Ignoring upgrades of {}",
+            numeric!(ignored.len(), "package"["s"])
+        );
+        print_version_list(&ignored);
+    }
+
+    // VCS packages are checked before reporting the skipped downgrades, as a
+    // stale AUR version doesn't mean there are no new upstream commits
+    let devel_upgrades = if args.devel {
+        tracing::info!("Checking VCS packages for upstream changes");
+        let outdated = devel::outdated_packages(&installed)
+            .await
+            .silent_unwrap(AppExitCode::Other)
+            .into_iter()
+            .filter(|(p, _)| !ignore_list.is_ignored(p));
+        take_devel_upgrades(outdated, &mut unchanged, &mut local_newer)
+    } else {
+        Vec::new()
+    };
+
+    if !local_newer.is_empty() {
+        if args.allow_downgrade {
+            tracing::info!(
+                "Downgrading {} to the AUR version",
+                numeric!(local_newer.len(), "package"["s"])
+            );
+        } else {
+            tracing::info!(
+                "Skipping {} with a newer local version (use --allow-downgrade to downgrade)",
+                numeric!(local_newer.len(), "package"["s"])
+            );
+        }
+        print_version_list(&local_newer);
+    }
+
+    let mut candidates = newer_upstream;
+
+    if args.allow_downgrade {
+        candidates.extend(local_newer);
+    }
+
+    if !devel_upgrades.is_empty() {
+        tracing::info!(
+            "Found {} with upstream changes",
+            numeric!(devel_upgrades.len(), "VCS package"["s"])
+        );
+        candidates.extend(devel_upgrades);
+    }
+
+    let mut temporary_ignores = TemporaryIgnores::load().unwrap_or_else(|e| {
+        tracing::warn!("Failed to load the deselected upgrades: {e}");
+        TemporaryIgnores::default()
+    });
+    let (deselected_before, candidates): (Vec<_>, Vec<_>) = candidates
+        .into_iter()
+        .partition(|c| temporary_ignores.is_ignored(&c.name, c.ignored_version()));
+
+    if !deselected_before.is_empty() {
+        tracing::info!(
+            "Skipping {} deselected during a previous upgrade",
+            numeric!(deselected_before.len(), "package"["s"])
+        );
+        print_version_list(&deselected_before);
+    }
+
+    if !candidates.is_empty() {
+        tracing::info!(
+            "Found {} for installed AUR packages",
+            numeric!(candidates.len(), "upgrade"["s"])
+        );
+        if options.json {
+            print_json("upgrade_candidates", json!({ "packages": candidates }));
+        } else {
+            print_upgrade_table(&candidates);
+        }
+        let aur_upgrades = select_upgrades(candidates, &mut temporary_ignores, options);
+        temporary_ignores.retain_installed(&installed);
+
+        if !options.dry_run {
+            if let Err(e) = temporary_ignores.save() {
+                tracing::warn!("Failed to save the deselected upgrades: {e}");
+            }
+        }
+        if aur_upgrades.is_empty() {
+            tracing::info!("No AUR packages selected for upgrading");
+        } else {
+            let options = Options {
+                upgrade: true,
+                ..options
+            };
+            aur_install(aur_upgrades, options).await;
+        }
     } else {
         tracing::info!("No upgrades available for installed AUR packages");
     }
 
-    tracing::info!("Scanning for .pacnew files post-upgrade");
-    detect().await;
+    if !options.dry_run {
+        tracing::info!("Scanning for .pacnew files post-upgrade");
+        detect().await;
+    }
+}
+
+/// Takes the VCS packages with new upstream commits out of the unchanged packages
+/// and the ones with a newer local version and turns them into devel upgrades
+fn take_devel_upgrades(
+    outdated: impl IntoIterator<Item = (String, String)>,
+    unchanged: &mut HashMap<String, AurVersionInfo>,
+    local_newer: &mut Vec<AurVersionInfo>,
+) -> Vec<AurVersionInfo> {
+    outdated
+        .into_iter()
+        .filter_map(|(name, revision)| {
+            let info = unchanged.remove(&name).or_else(|| {
+                let index = local_newer.iter().position(|i| i.name == name)?;
+                Some(local_newer.remove(index))
+            })?;
+            Some(AurVersionInfo {
+                devel: true,
+                remote_revision: Some(revision),
+                ..info
+            })
+        })
+        .collect()
+}
+
+/// Lets the user deselect upgrades and optionally skips the deselected
+/// packages until a newer version is available
+fn select_upgrades(
+    candidates: Vec<AurVersionInfo>,
+    temporary_ignores: &mut TemporaryIgnores,
+    options: Options,
+) -> Vec<String> {
+    if options.noconfirm || options.dry_run {
+        return candidates.into_iter().map(|c| c.name).collect();
+    }
+    let names: Vec<&str> = candidates.iter().map(|c| c.name.as_str()).collect();
+    let selection = multi_select!(default all, &names, "Select packages to upgrade");
+    let (selected, deselected): (Vec<_>, Vec<_>) = candidates
+        .into_iter()
+        .enumerate()
+        .partition(|(i, _)| selection.contains(i));
+
+    if !deselected.is_empty()
+        && prompt!(default no, "Skip the deselected packages until a newer version is available?")
+    {
+        for (_, info) in deselected {
+            temporary_ignores.add(info.name.clone(), info.ignored_version().to_owned());
+        }
+    }
+
+    selected.into_iter().map(|(_, c)| c.name).collect()
+}
+
+fn print_upgrade_table(packages: &[AurVersionInfo]) {
+    get_logger().print_list(
+        packages.iter().map(|p| {
+            let remote_version = if p.devel {
+                format!("{} (new commits)", p.remote_version)
+            } else {
+                p.remote_version.clone()
+            };
+            let out_of_date = if p.out_of_date.is_some() {
+                "[out of date]"
+            } else {
+                ""
+            };
+            format!(
+                "{} {} -> {} {} {}",
+                console::pad_str(&p.name, 30, Alignment::Left, Some("...")).bold(),
+                console::pad_str(&p.local_version, 20, Alignment::Left, Some("...")).dim(),
+                console::pad_str(&remote_version, 30, Alignment::Left, Some("...")).green(),
+                last_modified(p).dim(),
+                out_of_date.red()
+            )
+        }),
+        "\n",
+        2,
+    );
+}
+
+/// Formats the last modification date. Packages from PKGBUILD repositories don't have one.
+fn last_modified(package: &AurVersionInfo) -> String {
+    if package.last_modified == 0 {
+        return String::new();
+    }
+    Local
+        .timestamp(package.last_modified.try_into().unwrap(), 0)
+        .date_naive()
+        .to_string()
+}
+
+fn print_version_list(packages: &[AurVersionInfo]) {
+    get_logger().print_list(
+        packages.iter().map(|p| {
+            format!(
+                "{} {} -> {}",
+                p.name.clone().bold(),
+                p.local_version.clone().dim(),
+                p.remote_version.clone().green()
+            )
+        }),
+        "\n",
+        2,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{take_devel_upgrades, AurVersionInfo, VersionState};
+
+    fn version_info(name: &str, local_version: &str, remote_version: &str) -> AurVersionInfo {
+        AurVersionInfo {
+            name: name.to_owned(),
+            local_version: local_version.to_owned(),
+            remote_version: remote_version.to_owned(),
+            last_modified: 0,
+            out_of_date: None,
+            devel: false,
+            remote_revision: None,
+        }
+    }
+
+    #[test]
+    fn offers_devel_upgrades_for_local_newer_vcs_packages() {
+        let mut unchanged = HashMap::from([(
+            "bar-git".to_owned(),
+            version_info("bar-git", "r10-1", "r10-1"),
+        )]);
+        let mut local_newer = vec![
+            version_info("foo-git", "r123-1", "r100-1"),
+            version_info("baz", "2.0-1", "1.0-1"),
+        ];
+        let outdated = vec![
+            ("foo-git".to_owned(), "abc123".to_owned()),
+            ("bar-git".to_owned(), "def456".to_owned()),
+            ("missing-git".to_owned(), "0123ab".to_owned()),
+        ];
+
+        let upgrades = take_devel_upgrades(outdated, &mut unchanged, &mut local_newer);
+
+        let names: Vec<&str> = upgrades.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, ["foo-git", "bar-git"]);
+        assert!(upgrades.iter().all(|u| u.devel));
+        assert_eq!(upgrades[0].remote_revision.as_deref(), Some("abc123"));
+        assert_eq!(upgrades[0].local_version, "r123-1");
+        assert!(unchanged.is_empty());
+        assert_eq!(local_newer.len(), 1);
+        assert_eq!(local_newer[0].name, "baz");
+    }
+
+    #[test]
+    fn compares_versions_like_pacman() {
+        assert!(matches!(
+            VersionState::compare("1.0.0-1", "1.0.1-1"),
+            VersionState::NewerUpstream
+        ));
+        assert!(matches!(
+            VersionState::compare("1.0.0-2", "1.0.0-1"),
+            VersionState::LocalNewer
+        ));
+        assert!(matches!(
+            VersionState::compare("1.0.0-1", "1.0.0-1"),
+            VersionState::Equal
+        ));
+    }
+
+    #[test]
+    fn compares_epochs_and_pre_releases() {
+        assert!(matches!(
+            VersionState::compare("2.0-1", "1:1.0-1"),
+            VersionState::NewerUpstream
+        ));
+        assert!(matches!(
+            VersionState::compare("1.0-1", "1.0rc1-1"),
+            VersionState::LocalNewer
+        ));
+        assert!(matches!(
+            VersionState::compare("1.10-1", "1.9-1"),
+            VersionState::LocalNewer
+        ));
+    }
 }